@@ -7,7 +7,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     let tree = Tree::new(doc.clone(), "test")?;
 
     let _sub = tree.on_change(|e| match e {
-        TreeEvent::TreeUpdated(tree) => {
+        TreeEvent::TreeUpdated(tree, _origin) => {
             println!("{}", tree);
         }
         TreeEvent::TreePoisoned(_tree, err) => {
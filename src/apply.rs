@@ -0,0 +1,27 @@
+use crate::{ConflictResolution, NodeId};
+
+/// How the tree changed when an update was applied with
+/// [`Tree::apply_update`](crate::Tree::apply_update). Each list is sorted by node ID.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ApplyOutcome {
+    /// The nodes that were created.
+    pub created: Vec<NodeId>,
+    /// The nodes that were moved to a new parent or to a new position within their
+    /// parent.
+    pub moved: Vec<NodeId>,
+    /// The nodes that were deleted, including those deleted along with a parent.
+    pub deleted: Vec<NodeId>,
+    /// The conflicts the tree resolved to apply the update; see [`ConflictResolution`].
+    pub conflicts: Vec<ConflictResolution>,
+}
+
+impl ApplyOutcome {
+    /// Returns `true` if the update didn't change the structure of the tree. The update
+    /// may still have changed node data.
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty()
+            && self.moved.is_empty()
+            && self.deleted.is_empty()
+            && self.conflicts.is_empty()
+    }
+}
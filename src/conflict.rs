@@ -0,0 +1,34 @@
+use crate::NodeId;
+
+/// A change the tree made to its structure to resolve conflicting edits from
+/// different clients, as returned by [`Tree::take_conflicts`](crate::Tree::take_conflicts).
+///
+/// Resolutions depend only on the contents of the Yrs document, so every client
+/// that has received the same updates resolves conflicts the same way.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ConflictResolution {
+    /// Concurrent moves made `reparented` a descendant of itself. Its move under
+    /// `kept` was undone, keeping the moves that put `kept` where it is, and it was
+    /// placed under `new_parent`, the most recent of its previous parents outside
+    /// of the cycle.
+    CycleBroken {
+        kept: NodeId,
+        reparented: NodeId,
+        new_parent: NodeId,
+    },
+    /// `node` was moved under `deleted_parent` while another client deleted that
+    /// parent. The move was undone by placing the node under `new_parent`, the most
+    /// recent of its previous parents that still exists.
+    MovedIntoDeleted {
+        node: NodeId,
+        deleted_parent: NodeId,
+        new_parent: NodeId,
+    },
+    /// `node` was created under `deleted_parent`, or moved there with no other
+    /// parent to go back to, while another client deleted that parent. The node was
+    /// deleted along with its parent, like the parent's other descendants.
+    RemovedWithParent {
+        node: NodeId,
+        deleted_parent: NodeId,
+    },
+}
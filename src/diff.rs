@@ -0,0 +1,84 @@
+use crate::NodeId;
+
+/// A single difference between two trees, as returned by [`Tree::diff`](crate::Tree::diff).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeDiff {
+    /// The node exists only in the other tree.
+    Added(NodeId),
+    /// The node exists only in this tree.
+    Removed(NodeId),
+    /// The node has a different parent in the other tree.
+    Moved {
+        id: NodeId,
+        from_parent: NodeId,
+        to_parent: NodeId,
+    },
+    /// The children the parent has in both trees are in a different order.
+    Reordered { parent: NodeId },
+    /// The value stored on the node at the given key differs between the trees,
+    /// including when it's only set in one of them.
+    DataChanged { id: NodeId, key: String },
+}
+
+/// One step of an edit script that turns one tree into another, as returned by
+/// [`Tree::edit_script`](crate::Tree::edit_script) and applied with
+/// [`Tree::apply_edits`](crate::Tree::apply_edits).
+///
+/// Indices are positions among the parent's children: `old_index` in the tree the
+/// script was computed from, and `index` and `new_index` in the target tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TreeEdit {
+    /// The node is created under `parent`. Its data is set by separate
+    /// [`TreeEdit::DataSet`] steps.
+    Create {
+        id: NodeId,
+        parent: NodeId,
+        index: usize,
+    },
+    /// The node is deleted. Its children are either deleted by steps of their own,
+    /// which come first, or moved away by earlier steps.
+    Delete { id: NodeId },
+    /// The node is moved to a new parent, or to a new position under the same parent.
+    Move {
+        id: NodeId,
+        old_parent: NodeId,
+        new_parent: NodeId,
+        old_index: usize,
+        new_index: usize,
+    },
+    /// The value at the given key is set on the node, either because it changed or
+    /// because it wasn't set before.
+    DataSet {
+        id: NodeId,
+        key: String,
+        value: yrs::Any,
+    },
+    /// The value at the given key is removed from the node.
+    DataRemoved { id: NodeId, key: String },
+}
+
+/// Returns the longest strictly increasing subsequence of `values`.
+pub(crate) fn longest_increasing_subsequence(values: &[usize]) -> Vec<usize> {
+    // `tails[k]` is the position of the smallest value ending an increasing run of
+    // length `k + 1`, and `prev` links each position to the one before it in its run
+    let mut tails: Vec<usize> = vec![];
+    let mut prev = vec![None; values.len()];
+    for (i, value) in values.iter().enumerate() {
+        let k = tails.partition_point(|&j| values[j] < *value);
+        prev[i] = k.checked_sub(1).map(|k| tails[k]);
+        if k == tails.len() {
+            tails.push(i);
+        } else {
+            tails[k] = i;
+        }
+    }
+
+    let mut result = vec![];
+    let mut next = tails.last().copied();
+    while let Some(i) = next {
+        result.push(values[i]);
+        next = prev[i];
+    }
+    result.reverse();
+    result
+}
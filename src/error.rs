@@ -1,20 +1,78 @@
-use std::{error::Error, fmt};
+use std::{error::Error, fmt, sync::Arc};
 
 use crate::node::NodeId;
 
 /// Errors that can occur when manipulating a tree.
 #[derive(Clone)]
 pub enum TreeError {
+    /// Moving the first node under the second would make the node its own ancestor.
     Cycle(NodeId, NodeId),
+    /// The parent node for the operation doesn't exist in the tree.
     MissingParent(NodeId),
+    /// The node can't be the target of the operation, such as the root node for a
+    /// move or a delete.
     InvalidTarget(NodeId),
+    /// The operation isn't supported, usually on the root node.
     UnsupportedOperation(String),
+    /// A node ID is invalid for the operation, such as an ID that is already in use.
     InvalidId(String),
+    /// The Yrs document doesn't contain a valid tree.
     BadYrsDoc(String),
+    /// A previous error left the tree in an inconsistent state, so it can no longer be
+    /// used. The error that poisoned the tree is available from [`Error::source`].
     TreePoisoned(Box<TreeError>),
+    /// A transaction on the underlying Yrs document could not be acquired, usually
+    /// because another transaction is still active. This is the case inside event
+    /// callbacks, which run while the transaction that triggered them is committed.
+    /// The Yrs error is available from [`Error::source`].
+    TransactionFailed(Arc<yrs::TransactionAcqError>),
+    /// The operation would place the node, or one of its descendants, deeper than
+    /// the tree's [`max_depth`](crate::TreeOptions::max_depth).
+    MaxDepthExceeded(NodeId, usize),
+    /// The operation would give the node more children than the tree's
+    /// [`max_children_per_node`](crate::TreeOptions::max_children_per_node).
+    MaxChildrenExceeded(NodeId, usize),
+    /// The index is past the end of the children of the given parent node.
+    IndexOutOfRange(NodeId, usize),
+    /// A value could not be converted to a Yrs value.
+    SerializationFailed(String),
+    /// An update passed to [`Tree::apply_update`](crate::Tree::apply_update) could not
+    /// be decoded or applied, or a state vector passed to
+    /// [`Tree::encode_state_from`](crate::Tree::encode_state_from) could not be decoded.
+    InvalidUpdate(String),
+    /// The named shared type isn't a tree; see [`Tree::open`](crate::Tree::open).
+    NotATree(String),
+    /// The row at the given position in the rows passed to
+    /// [`Tree::from_rows`](crate::Tree::from_rows) doesn't fit with the others, for the
+    /// reason given.
+    InvalidRow(usize, String),
+    /// The line with the given number, counting from 1, of the outline passed to
+    /// [`Tree::import_outline`](crate::Tree::import_outline) is indented incorrectly.
+    InvalidOutline(usize, String),
+    /// The [`SubtreeExport`](crate::SubtreeExport) passed to
+    /// [`Node::import_subtree`](crate::Node::import_subtree) doesn't describe a single
+    /// subtree, for the reason given. This can only happen with an export that was
+    /// deserialized from malformed input.
+    InvalidExport(String),
+    /// The value stored at `key` could not be deserialized into `expected_type`.
+    /// The underlying deserialization error is available from [`Error::source`].
+    DeserializationFailed {
+        key: String,
+        expected_type: &'static str,
+        source: Arc<dyn Error + Send + Sync>,
+    },
 }
 
-impl Error for TreeError {}
+impl Error for TreeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TreeError::DeserializationFailed { source, .. } => Some(&**source),
+            TreeError::TransactionFailed(source) => Some(&**source),
+            TreeError::TreePoisoned(source) => Some(&**source),
+            _ => None,
+        }
+    }
+}
 
 impl fmt::Debug for TreeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -28,6 +86,29 @@ impl fmt::Debug for TreeError {
             TreeError::InvalidId(id) => write!(f, "InvalidId({})", id),
             TreeError::BadYrsDoc(msg) => write!(f, "BadYrsDoc({})", msg),
             TreeError::TreePoisoned(msg) => write!(f, "TreePoisoned({})", msg),
+            TreeError::TransactionFailed(msg) => write!(f, "TransactionFailed({})", msg),
+            TreeError::MaxDepthExceeded(id, max) => write!(f, "MaxDepthExceeded({}, {})", id, max),
+            TreeError::MaxChildrenExceeded(id, max) => {
+                write!(f, "MaxChildrenExceeded({}, {})", id, max)
+            }
+            TreeError::IndexOutOfRange(parent, index) => {
+                write!(f, "IndexOutOfRange({}, {})", parent, index)
+            }
+            TreeError::SerializationFailed(msg) => write!(f, "SerializationFailed({})", msg),
+            TreeError::InvalidUpdate(msg) => write!(f, "InvalidUpdate({})", msg),
+            TreeError::NotATree(name) => write!(f, "NotATree({})", name),
+            TreeError::InvalidRow(row, msg) => write!(f, "InvalidRow({}, {})", row, msg),
+            TreeError::InvalidOutline(line, msg) => write!(f, "InvalidOutline({}, {})", line, msg),
+            TreeError::InvalidExport(msg) => write!(f, "InvalidExport({})", msg),
+            TreeError::DeserializationFailed {
+                key,
+                expected_type,
+                source,
+            } => write!(
+                f,
+                "DeserializationFailed({}, {}, {})",
+                key, expected_type, source
+            ),
         }
     }
 }
@@ -35,14 +116,20 @@ impl fmt::Debug for TreeError {
 impl fmt::Display for TreeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            TreeError::Cycle(child, parent) => {
-                write!(f, "Operation would create a cycle: {} -> {}", child, parent)
-            }
+            TreeError::Cycle(child, parent) => write!(
+                f,
+                "Moving node {} under node {} would create a cycle",
+                child, parent
+            ),
             TreeError::MissingParent(parent) => {
-                write!(f, "Requested parent node does not exist: {}", parent)
+                write!(f, "Parent node {} does not exist", parent)
             }
             TreeError::InvalidTarget(target) => {
-                write!(f, "Invalid target: {}", target)
+                write!(
+                    f,
+                    "Node {} is not a valid target for this operation",
+                    target
+                )
             }
             TreeError::UnsupportedOperation(operation) => {
                 write!(f, "Unsupported operation: {}", operation)
@@ -50,6 +137,47 @@ impl fmt::Display for TreeError {
             TreeError::InvalidId(id) => write!(f, "Invalid ID: {}", id),
             TreeError::BadYrsDoc(msg) => write!(f, "Malformed Yrs doc: {}", msg),
             TreeError::TreePoisoned(msg) => write!(f, "Tree has been poisoned: {}", msg),
+            TreeError::TransactionFailed(msg) => {
+                write!(f, "Could not acquire a Yrs transaction: {}", msg)
+            }
+            TreeError::MaxDepthExceeded(id, max) => {
+                write!(
+                    f,
+                    "Node {} would be deeper than the maximum depth of {}",
+                    id, max
+                )
+            }
+            TreeError::MaxChildrenExceeded(id, max) => {
+                write!(f, "Node {} would have more than {} children", id, max)
+            }
+            TreeError::IndexOutOfRange(parent, index) => write!(
+                f,
+                "Index {} is past the end of the children of node {}",
+                index, parent
+            ),
+            TreeError::SerializationFailed(msg) => write!(f, "Serialization failed: {}", msg),
+            TreeError::InvalidUpdate(msg) => write!(f, "Invalid Yrs update: {}", msg),
+            TreeError::NotATree(name) => write!(f, "No tree named {} in the Yrs doc", name),
+            TreeError::InvalidRow(row, msg) => write!(f, "Invalid row {}: {}", row, msg),
+            TreeError::InvalidOutline(line, msg) => {
+                write!(f, "Invalid outline at line {}: {}", line, msg)
+            }
+            TreeError::InvalidExport(msg) => write!(f, "Invalid subtree export: {}", msg),
+            TreeError::DeserializationFailed {
+                key,
+                expected_type,
+                source,
+            } => write!(
+                f,
+                "Could not deserialize the value at key {} as {}: {}",
+                key, expected_type, source
+            ),
         }
     }
 }
+
+impl From<yrs::TransactionAcqError> for TreeError {
+    fn from(error: yrs::TransactionAcqError) -> Self {
+        TreeError::TransactionFailed(Arc::new(error))
+    }
+}
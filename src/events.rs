@@ -7,24 +7,163 @@ use std::{
 };
 
 use parking_lot::RwLock;
+use yrs::{
+    types::{EntryChange, PathSegment},
+    Out,
+};
 
-use crate::{Tree, TreeError};
+use crate::{tree_structure::TreeStructure, NodeId, SubtreeExport, Tree, TreeError};
 
 /// An event that is emitted when the tree changes.
 #[derive(Debug, Clone)]
 pub enum TreeEvent {
-    TreeUpdated(Arc<Tree>),
+    TreeUpdated(Arc<Tree>, EventOrigin),
     TreePoisoned(Arc<Tree>, TreeError),
 }
 
+/// Where the change that caused a [`TreeEvent::TreeUpdated`] event came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventOrigin {
+    /// The origin of the Yrs transaction that made the change, if it had one.
+    pub origin: Option<yrs::Origin>,
+    /// Whether the change was made by this tree (including undo and redo), as opposed
+    /// to being applied from another client or another part of the document.
+    pub is_local: bool,
+}
+
+/// An event that is emitted when a node, or a node in its subtree, changes.
+/// See [`Node::on_change`](crate::Node::on_change).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeEvent {
+    /// The node was created.
+    Created(NodeId),
+    /// The node was deleted.
+    Deleted(NodeId),
+    /// The node was moved to a new parent or to a new position within its parent.
+    /// The old position is the one the tree had before the change was applied.
+    Moved {
+        id: NodeId,
+        old_parent: NodeId,
+        old_index: usize,
+        new_parent: NodeId,
+        new_index: usize,
+    },
+    /// Data stored on the node was changed.
+    DataChanged(NodeId),
+}
+
+impl NodeEvent {
+    /// Returns the ID of the node the event refers to.
+    pub fn id(&self) -> &NodeId {
+        match self {
+            NodeEvent::Created(id)
+            | NodeEvent::Deleted(id)
+            | NodeEvent::Moved { id, .. }
+            | NodeEvent::DataChanged(id) => id,
+        }
+    }
+}
+
+/// The changes made to the data stored on a node by a single transaction.
+/// See [`Node::observe_data`](crate::Node::observe_data).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataEvent {
+    /// The ID of the node whose data changed.
+    pub id: NodeId,
+    /// The changed keys, ordered by key.
+    pub changes: Vec<DataChange>,
+}
+
+/// A change to the value stored at a key on a node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataChange {
+    /// The key that changed.
+    pub key: String,
+    /// The previous value, if the key was set and the value is known. Changes made
+    /// inside a shared type stored at the key have no previous value.
+    pub old_value: Option<Out>,
+    /// The new value, or `None` if the key was removed.
+    pub new_value: Option<Out>,
+    /// For changes made inside a shared type stored at the key, the path from the
+    /// key's value to the shared type that changed, which may be nested several levels
+    /// deep. Empty when the key itself was set or removed.
+    pub path: Vec<PathSegment>,
+}
+
+impl DataChange {
+    pub(crate) fn from_entry_change(key: &str, change: &EntryChange) -> Self {
+        let (old_value, new_value) = match change {
+            EntryChange::Inserted(new) => (None, Some(new.clone())),
+            EntryChange::Updated(old, new) => (Some(old.clone()), Some(new.clone())),
+            EntryChange::Removed(old) => (Some(old.clone()), None),
+        };
+
+        Self {
+            key: key.to_string(),
+            old_value,
+            new_value,
+            path: vec![],
+        }
+    }
+}
+
+/// Which changes a node subscription is notified about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeScope {
+    /// Only changes to the node itself.
+    Node,
+    /// Changes to the node and to any of its descendants, including nodes
+    /// that are moved into or out of the subtree.
+    Subtree,
+}
+
+type TreeCallback = Arc<dyn Fn(&TreeEvent) + Send + Sync>;
+type BatchCallback = Arc<dyn Fn(&[NodeEvent]) + Send + Sync>;
+type DataCallback = Arc<dyn Fn(&DataEvent) + Send + Sync>;
+type DeleteCallback = Arc<dyn Fn(&SubtreeExport) + Send + Sync>;
+
+#[derive(Clone)]
+struct NodeListener {
+    id: NodeId,
+    scope: ChangeScope,
+    callback: Arc<dyn Fn(&NodeEvent) + Send + Sync>,
+}
+
+impl NodeListener {
+    fn is_interested(&self, event: &NodeEvent, old: &TreeStructure, new: &TreeStructure) -> bool {
+        if event.id() == &self.id {
+            return true;
+        }
+
+        match self.scope {
+            ChangeScope::Node => false,
+            ChangeScope::Subtree => {
+                new.is_descendant_of(event.id(), &self.id)
+                    || old.is_descendant_of(event.id(), &self.id)
+            }
+        }
+    }
+}
+
 /// An observer that can subscribe to tree update events.
 pub struct TreeObserver {
     next_id: AtomicUsize,
-    listeners: RwLock<HashMap<usize, Box<dyn Fn(&TreeEvent) + Send + Sync>>>,
+    listeners: RwLock<HashMap<usize, TreeCallback>>,
+    node_listeners: RwLock<HashMap<usize, NodeListener>>,
+    batch_listeners: RwLock<HashMap<usize, BatchCallback>>,
+    data_listeners: RwLock<HashMap<usize, (Option<NodeId>, DataCallback)>>,
+    delete_listeners: RwLock<HashMap<usize, DeleteCallback>>,
 }
 
 /// A subscription to a tree update event.
 /// When dropped, the subscription is automatically cancelled.
+///
+/// Callbacks run while the transaction that triggered them is being committed. Reading
+/// the tree's structure (e.g. [`Tree::get_node`] or [`NodeApi::children`](crate::NodeApi::children))
+/// works as usual inside a callback, but operations that need their own transaction on the
+/// Yrs document, such as modifying the tree or reading node data, return a
+/// [`TreeError::TransactionFailed`] error. Subscriptions may be created and dropped from
+/// inside a callback.
 pub struct Subscription {
     id: usize,
     observer: Weak<TreeObserver>,
@@ -41,6 +180,10 @@ impl TreeObserver {
         Self {
             next_id: AtomicUsize::new(0),
             listeners: RwLock::new(HashMap::new()),
+            node_listeners: RwLock::new(HashMap::new()),
+            batch_listeners: RwLock::new(HashMap::new()),
+            data_listeners: RwLock::new(HashMap::new()),
+            delete_listeners: RwLock::new(HashMap::new()),
         }
     }
 
@@ -49,7 +192,7 @@ impl TreeObserver {
         callback: impl Fn(&TreeEvent) + Send + Sync + 'static,
     ) -> Subscription {
         let id = self.next_id.fetch_add(1, Ordering::Relaxed);
-        self.listeners.write().insert(id, Box::new(callback));
+        self.listeners.write().insert(id, Arc::new(callback));
 
         Subscription {
             id,
@@ -58,17 +201,308 @@ impl TreeObserver {
     }
 
     pub fn notify(&self, event: &TreeEvent) {
-        let listeners = self.listeners.read();
-        for callback in listeners.values() {
-            callback(event);
+        // Release the lock before calling back so callbacks can (un)subscribe
+        let listeners = self
+            .listeners
+            .read()
+            .iter()
+            .map(|(id, callback)| (*id, callback.clone()))
+            .collect::<Vec<_>>();
+
+        for (id, callback) in listeners {
+            // Skip listeners unsubscribed by an earlier callback
+            if self.listeners.read().contains_key(&id) {
+                callback(event);
+            }
+        }
+    }
+
+    pub(crate) fn subscribe_node(
+        self: &Arc<Self>,
+        id: NodeId,
+        scope: ChangeScope,
+        callback: impl Fn(&NodeEvent) + Send + Sync + 'static,
+    ) -> Subscription {
+        let sub_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.node_listeners.write().insert(
+            sub_id,
+            NodeListener {
+                id,
+                scope,
+                callback: Arc::new(callback),
+            },
+        );
+
+        Subscription {
+            id: sub_id,
+            observer: Arc::downgrade(self),
         }
     }
+
+    pub(crate) fn subscribe_batch(
+        self: &Arc<Self>,
+        callback: impl Fn(&[NodeEvent]) + Send + Sync + 'static,
+    ) -> Subscription {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.batch_listeners.write().insert(id, Arc::new(callback));
+
+        Subscription {
+            id,
+            observer: Arc::downgrade(self),
+        }
+    }
+
+    /// Subscribes to data changes on the node with the given ID, or on every node if
+    /// `id` is `None`.
+    pub(crate) fn subscribe_data(
+        self: &Arc<Self>,
+        id: Option<NodeId>,
+        callback: impl Fn(&DataEvent) + Send + Sync + 'static,
+    ) -> Subscription {
+        let sub_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.data_listeners
+            .write()
+            .insert(sub_id, (id, Arc::new(callback)));
+
+        Subscription {
+            id: sub_id,
+            observer: Arc::downgrade(self),
+        }
+    }
+
+    pub(crate) fn subscribe_deleted(
+        self: &Arc<Self>,
+        callback: impl Fn(&SubtreeExport) + Send + Sync + 'static,
+    ) -> Subscription {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.delete_listeners.write().insert(id, Arc::new(callback));
+
+        Subscription {
+            id,
+            observer: Arc::downgrade(self),
+        }
+    }
+
+    pub(crate) fn has_delete_listeners(&self) -> bool {
+        !self.delete_listeners.read().is_empty()
+    }
+
+    pub(crate) fn notify_deleted(&self, subtrees: &[SubtreeExport]) {
+        if subtrees.is_empty() {
+            return;
+        }
+
+        // Release the lock before calling back so callbacks can (un)subscribe
+        let listeners = self
+            .delete_listeners
+            .read()
+            .iter()
+            .map(|(id, callback)| (*id, callback.clone()))
+            .collect::<Vec<_>>();
+
+        for (id, callback) in listeners {
+            for subtree in subtrees {
+                // Skip listeners unsubscribed by an earlier callback
+                if self.delete_listeners.read().contains_key(&id) {
+                    callback(subtree);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn has_node_listeners(&self) -> bool {
+        !self.node_listeners.read().is_empty()
+            || !self.batch_listeners.read().is_empty()
+            || !self.data_listeners.read().is_empty()
+    }
+
+    pub(crate) fn notify_data(&self, events: &[DataEvent]) {
+        if events.is_empty() {
+            return;
+        }
+
+        // Release the lock before calling back so callbacks can (un)subscribe
+        let listeners = self
+            .data_listeners
+            .read()
+            .iter()
+            .map(|(sub_id, (id, callback))| (*sub_id, id.clone(), callback.clone()))
+            .collect::<Vec<_>>();
+
+        for (sub_id, id, callback) in listeners {
+            for event in events.iter().filter(|e| match &id {
+                Some(id) => &e.id == id,
+                None => true,
+            }) {
+                // Skip listeners unsubscribed by an earlier callback
+                if self.data_listeners.read().contains_key(&sub_id) {
+                    callback(event);
+                }
+            }
+        }
+    }
+
+    /// Notifies node listeners about the given events. `old` and `new` are the tree
+    /// structures before and after the change, and are used to determine which
+    /// subtrees an event affects.
+    pub(crate) fn notify_nodes(
+        &self,
+        old: &TreeStructure,
+        new: &TreeStructure,
+        events: &[NodeEvent],
+    ) {
+        if events.is_empty() {
+            return;
+        }
+
+        let batch_listeners = self
+            .batch_listeners
+            .read()
+            .iter()
+            .map(|(id, callback)| (*id, callback.clone()))
+            .collect::<Vec<_>>();
+
+        for (id, callback) in batch_listeners {
+            if self.batch_listeners.read().contains_key(&id) {
+                callback(events);
+            }
+        }
+
+        // Release the lock before calling back so callbacks can (un)subscribe
+        let listeners = self
+            .node_listeners
+            .read()
+            .iter()
+            .map(|(id, listener)| (*id, listener.clone()))
+            .collect::<Vec<_>>();
+
+        let mut finished = vec![];
+        for (sub_id, listener) in listeners.iter() {
+            for event in events {
+                // Skip listeners unsubscribed by an earlier callback
+                if listener.is_interested(event, old, new)
+                    && self.node_listeners.read().contains_key(sub_id)
+                {
+                    (listener.callback)(event);
+                }
+
+                // Subscriptions follow their node, so once it's gone they're done
+                if let NodeEvent::Deleted(id) = event {
+                    if id == &listener.id {
+                        finished.push(*sub_id);
+                    }
+                }
+            }
+        }
+
+        if !finished.is_empty() {
+            let mut listeners = self.node_listeners.write();
+            for sub_id in finished {
+                listeners.remove(&sub_id);
+            }
+        }
+
+        // Data subscriptions end along with their node as well
+        self.data_listeners.write().retain(|_, (id, _)| {
+            let Some(id) = id else {
+                return true;
+            };
+            !events
+                .iter()
+                .any(|e| matches!(e, NodeEvent::Deleted(deleted) if deleted == id))
+        });
+    }
+}
+
+impl Subscription {
+    /// Cancels the subscription. This is equivalent to dropping it.
+    pub fn unsubscribe(self) {
+        drop(self);
+    }
 }
 
 impl Drop for Subscription {
     fn drop(&mut self) {
         if let Some(observer) = self.observer.upgrade() {
             observer.listeners.write().remove(&self.id);
+            observer.node_listeners.write().remove(&self.id);
+            observer.batch_listeners.write().remove(&self.id);
+            observer.data_listeners.write().remove(&self.id);
+            observer.delete_listeners.write().remove(&self.id);
+        }
+    }
+}
+
+/// A guard returned by [`Tree::pause_events`]. While any guard for a tree is alive,
+/// the tree's events are buffered; dropping the last one delivers them.
+#[must_use = "events are resumed as soon as the guard is dropped"]
+pub struct EventPause {
+    pub(crate) tree: Weak<Tree>,
+}
+
+impl EventPause {
+    /// Resumes event delivery. This is equivalent to dropping the guard.
+    pub fn resume(self) {
+        drop(self);
+    }
+}
+
+impl Drop for EventPause {
+    fn drop(&mut self) {
+        if let Some(tree) = self.tree.upgrade() {
+            tree.resume_events();
+        }
+    }
+}
+
+/// The events buffered while a tree's events are paused.
+#[derive(Clone, Default)]
+pub(crate) struct PausedEvents {
+    pub tree_events: Vec<TreeEvent>,
+    /// The structure before the first structural change made while paused.
+    pub structure: Option<TreeStructure>,
+    pub data_events: Vec<DataEvent>,
+}
+
+impl PausedEvents {
+    pub fn push_tree_event(&mut self, event: TreeEvent) {
+        // Only the latest update is kept, since it already reflects the earlier ones
+        if matches!(event, TreeEvent::TreeUpdated(..)) {
+            self.tree_events
+                .retain(|e| !matches!(e, TreeEvent::TreeUpdated(..)));
+        }
+        self.tree_events.push(event);
+    }
+
+    pub fn push_data_events(&mut self, events: Vec<DataEvent>) {
+        for event in events {
+            let Some(existing) = self.data_events.iter_mut().find(|e| e.id == event.id) else {
+                self.data_events.push(event);
+                continue;
+            };
+
+            for change in event.changes {
+                match existing
+                    .changes
+                    .iter_mut()
+                    .find(|c| c.key == change.key && c.path == change.path)
+                {
+                    Some(existing_change) => existing_change.new_value = change.new_value,
+                    None => existing.changes.push(change),
+                }
+            }
+        }
+    }
+
+    /// Returns the net data changes, dropping keys that ended up with their
+    /// original value.
+    pub fn take_data_events(&mut self) -> Vec<DataEvent> {
+        let mut events = std::mem::take(&mut self.data_events);
+        for event in events.iter_mut() {
+            event.changes.retain(|c| c.old_value != c.new_value);
+            event.changes.sort_by(|a, b| a.key.cmp(&b.key));
         }
+        events.retain(|e| !e.changes.is_empty());
+        events
     }
 }
@@ -0,0 +1,16 @@
+/// What was removed by [`Tree::gc`](crate::Tree::gc).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GcStats {
+    /// The number of node containers removed, along with their data, because the
+    /// nodes couldn't be reached from the root node, and had already been marked for
+    /// removal by an earlier GC.
+    pub nodes_removed: usize,
+    /// The number of node containers marked for removal by the next GC, because the
+    /// nodes couldn't be reached from the root node.
+    pub nodes_marked: usize,
+    /// The number of entries removed from the parent histories of the remaining
+    /// nodes because they referred to nodes that no longer exist.
+    pub edges_removed: usize,
+    /// How much smaller the document's encoded state became.
+    pub bytes_freed: usize,
+}
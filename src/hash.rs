@@ -0,0 +1,79 @@
+use yrs::Any;
+
+/// A 64-bit FNV-1a hasher whose output only depends on the bytes written to it, so
+/// hashes are the same on every platform and with every Rust version, unlike
+/// [`std::hash::DefaultHasher`].
+pub(crate) struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl StableHasher {
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    pub(crate) fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    /// Writes the string prefixed with its length, so that consecutive strings can't
+    /// run into each other.
+    pub(crate) fn write_str(&mut self, value: &str) {
+        self.write_u64(value.len() as u64);
+        self.write(value.as_bytes());
+    }
+
+    pub(crate) fn write_any(&mut self, value: &Any) {
+        match value {
+            Any::Null => self.write(&[0]),
+            Any::Undefined => self.write(&[1]),
+            Any::Bool(value) => self.write(&[2, *value as u8]),
+            Any::Number(value) => {
+                self.write(&[3]);
+                self.write_u64(value.to_bits());
+            }
+            Any::BigInt(value) => {
+                self.write(&[4]);
+                self.write_u64(*value as u64);
+            }
+            Any::String(value) => {
+                self.write(&[5]);
+                self.write_str(value);
+            }
+            Any::Buffer(value) => {
+                self.write(&[6]);
+                self.write_u64(value.len() as u64);
+                self.write(value);
+            }
+            Any::Array(values) => {
+                self.write(&[7]);
+                self.write_u64(values.len() as u64);
+                for value in values.iter() {
+                    self.write_any(value);
+                }
+            }
+            Any::Map(entries) => {
+                // Maps have no order of their own, so entries are hashed by key
+                let mut entries = entries.iter().collect::<Vec<_>>();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                self.write(&[8]);
+                self.write_u64(entries.len() as u64);
+                for (key, value) in entries {
+                    self.write_str(key);
+                    self.write_any(value);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
@@ -0,0 +1,43 @@
+use crate::NodeId;
+
+/// A violation of the tree's structural invariants, as returned by
+/// [`Tree::check_integrity`](crate::Tree::check_integrity).
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum IntegrityError {
+    /// The node has no parent, or its parent is not in the tree.
+    #[error("{}", missing_parent_message(node, parent))]
+    MissingParent {
+        node: NodeId,
+        parent: Option<NodeId>,
+    },
+    /// `parent` lists `child` among its children, but `child` is not in the tree.
+    #[error("Child {child} of node {parent} does not exist")]
+    MissingChild { parent: NodeId, child: NodeId },
+    /// The node is not listed exactly once among the children of its parent, and
+    /// nowhere else. `listed_under` has an entry for each time it is listed.
+    #[error("Node {node} has parent {parent} but is listed as a child of {listed_under:?}")]
+    ParentMismatch {
+        node: NodeId,
+        parent: NodeId,
+        listed_under: Vec<NodeId>,
+    },
+    /// The node is its own ancestor.
+    #[error("Node {0} is its own ancestor")]
+    Cycle(NodeId),
+    /// The node's children are not sorted by fractional index and ID.
+    #[error("Children of node {0} are out of order")]
+    Misordered(NodeId),
+    /// The node has no container in the Yrs document.
+    #[error("Node {0} has no container in the Yrs doc")]
+    MissingContainer(NodeId),
+    /// The node's fractional index differs from the one in the Yrs document.
+    #[error("Fractional index of node {0} differs from the Yrs doc")]
+    IndexMismatch(NodeId),
+}
+
+fn missing_parent_message(node: &NodeId, parent: &Option<NodeId>) -> String {
+    match parent {
+        Some(parent) => format!("Parent {} of node {} does not exist", parent, node),
+        None => format!("Node {} has no parent", node),
+    }
+}
@@ -4,27 +4,54 @@ use std::sync::Arc;
 use crate::{tree_structure::TreeStructure, Node, NodeApi, NodeId, Tree};
 
 /// The traversal order for iterating over the nodes in a tree.
+///
+/// Given:
+///
+/// ```text
+/// <ROOT>
+/// ├──A
+/// │  ├──C
+/// │  └──D
+/// └──B
+/// ```
+///
+/// * `DepthFirst` yields `<ROOT>, A, C, D, B`
+/// * `BreadthFirst` yields `<ROOT>, A, B, C, D`
+/// * `PostOrder` yields `C, D, A, B, <ROOT>`
 #[derive(Clone, Copy)]
 pub enum TraversalOrder {
-    /// Depth-first traversal
+    /// Depth-first (pre-order) traversal: each node is yielded before its children,
+    /// and its whole subtree is yielded before its next sibling.
     DepthFirst,
-    /// Breadth-first traversal
+    /// Breadth-first traversal: each level of the tree is yielded before the next one.
     BreadthFirst,
+    /// Depth-first post-order traversal: each node is yielded after all of its
+    /// descendants, which is useful for computing values bottom-up.
+    PostOrder,
 }
 
-/// An iterator over the nodes in the tree in either depth-first or breadth-first order.
+/// An iterator over the nodes in the tree in the given [`TraversalOrder`].
 ///
 /// The iterator represents a snapshot of the tree at the time of the iterator's creation,
 /// and will not reflect changes to the tree after it was created.
+///
+/// Cloning the iterator saves its position: the clone yields the same remaining nodes
+/// as the original, and advancing one doesn't affect the other. Clones share the
+/// snapshot, so they are cheap to make.
+#[derive(Clone)]
 pub struct TreeIter {
     tree: Arc<Tree>,
-    structure: TreeStructure,
+    structure: Arc<TreeStructure>,
     order: TraversalOrder,
     start: NodeId,
-    // For BFS
-    queue: VecDeque<NodeId>,
+    // For BFS; each node is paired with its depth
+    queue: VecDeque<(NodeId, usize)>,
     // For DFS
     last_node: Option<NodeId>,
+    last_depth: usize,
+    // For post-order; each node is paired with whether its children have been pushed,
+    // and its depth
+    stack: Vec<(NodeId, bool, usize)>,
 }
 
 impl TreeIter {
@@ -32,12 +59,17 @@ impl TreeIter {
         let structure = {
             let lock = tree.structure.lock();
             let structure = lock.borrow().clone();
-            structure
+            Arc::new(structure)
         };
 
         let mut queue = VecDeque::new();
         if matches!(order, TraversalOrder::BreadthFirst) {
-            queue.push_back(start.clone());
+            queue.push_back((start.clone(), 0));
+        }
+
+        let mut stack = vec![];
+        if matches!(order, TraversalOrder::PostOrder) {
+            stack.push((start.clone(), false, 0));
         }
 
         Self {
@@ -47,25 +79,30 @@ impl TreeIter {
             queue,
             start: start.clone(),
             last_node: None,
+            last_depth: 0,
+            stack,
         }
     }
-}
 
-impl Iterator for TreeIter {
-    type Item = Arc<Node>;
+    /// Turns the iterator into one that yields each node along with its depth relative
+    /// to the node the traversal started at, which has a depth of 0. See
+    /// [`NodeApi::traverse_with_depth`].
+    pub fn with_depth(self) -> TreeIterWithDepth {
+        TreeIterWithDepth(self)
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
+    fn next_with_depth(&mut self) -> Option<(Arc<Node>, usize)> {
         match self.order {
             TraversalOrder::BreadthFirst => {
-                let current_id = self.queue.pop_front()?;
+                let (current_id, depth) = self.queue.pop_front()?;
 
                 if let Some(children) = self.structure.get_children(&current_id) {
                     for child in children {
-                        self.queue.push_back(child.clone());
+                        self.queue.push_back((child.clone(), depth + 1));
                     }
                 }
 
-                Some(Node::new(current_id, self.tree.clone()))
+                Some((Node::new(current_id, self.tree.clone()), depth))
             }
 
             TraversalOrder::DepthFirst => {
@@ -75,10 +112,15 @@ impl Iterator for TreeIter {
                     if !children.is_empty() {
                         let next_id = &children[0];
                         self.last_node = Some(next_id.clone());
-                        Some(Node::new(next_id.clone(), self.tree.clone()))
+                        self.last_depth += 1;
+                        Some((
+                            Node::new(next_id.clone(), self.tree.clone()),
+                            self.last_depth,
+                        ))
                     } else {
                         // No children, backtrack to find next sibling
                         let mut current = last_node.clone();
+                        let mut depth = self.last_depth;
                         loop {
                             // Stop if we've reached the start node while backtracking
                             if current == self.start {
@@ -92,22 +134,71 @@ impl Iterator for TreeIter {
                             if current_idx + 1 < siblings.len() {
                                 let next_id = &siblings[current_idx + 1];
                                 self.last_node = Some(next_id.clone());
-                                return Some(Node::new(next_id.clone(), self.tree.clone()));
+                                self.last_depth = depth;
+                                return Some((
+                                    Node::new(next_id.clone(), self.tree.clone()),
+                                    depth,
+                                ));
                             }
 
                             current = parent_id.clone();
+                            depth -= 1;
                         }
                     }
                 } else {
                     let start = Node::new(self.start.clone(), self.tree.clone());
                     self.last_node = Some(start.id().clone());
-                    Some(start)
+                    Some((start, 0))
                 }
             }
+
+            TraversalOrder::PostOrder => loop {
+                let (current_id, expanded, depth) = self.stack.pop()?;
+
+                if expanded {
+                    return Some((Node::new(current_id, self.tree.clone()), depth));
+                }
+
+                let children = self
+                    .structure
+                    .get_children(&current_id)
+                    .unwrap_or_default()
+                    .to_vec();
+                self.stack.push((current_id, true, depth));
+                // Push in reverse so the first child is visited first
+                for child in children.into_iter().rev() {
+                    self.stack.push((child, false, depth + 1));
+                }
+            },
         }
     }
 }
 
+impl Iterator for TreeIter {
+    type Item = Arc<Node>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_depth().map(|(node, _)| node)
+    }
+}
+
+/// An iterator over the nodes in the tree in the given [`TraversalOrder`], yielding
+/// each node along with its depth relative to the node the traversal started at.
+/// Returned by [`NodeApi::traverse_with_depth`] and [`TreeIter::with_depth`].
+///
+/// The depths are tracked as the iterator moves through the tree, rather than
+/// computed for each node.
+#[derive(Clone)]
+pub struct TreeIterWithDepth(TreeIter);
+
+impl Iterator for TreeIterWithDepth {
+    type Item = (Arc<Node>, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_with_depth()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{error::Error, sync::Arc};
@@ -152,6 +243,96 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_with_depth() -> Result<(), Box<dyn Error>> {
+        let tree = setup_tree()?;
+        tree.get_node("6").unwrap().create_child_with_id("10")?;
+
+        for order in [
+            TraversalOrder::DepthFirst,
+            TraversalOrder::BreadthFirst,
+            TraversalOrder::PostOrder,
+        ] {
+            let with_depth = tree.traverse_with_depth(order).collect::<Vec<_>>();
+            assert_eq!(
+                with_depth
+                    .iter()
+                    .map(|(node, _)| node.id().clone())
+                    .collect::<Vec<_>>(),
+                tree.traverse(order)
+                    .map(|node| node.id().clone())
+                    .collect::<Vec<_>>()
+            );
+            for (node, depth) in with_depth {
+                assert_eq!(depth, node.depth(), "{}", node.id());
+            }
+
+            // Depths are relative to the starting node
+            let node = tree.get_node("2").unwrap();
+            for (descendant, depth) in node.traverse_with_depth(order) {
+                assert_eq!(depth, descendant.depth() - 1, "{}", descendant.id());
+            }
+        }
+
+        let node = tree.get_node("2").unwrap();
+        let result = node
+            .traverse_with_depth(TraversalOrder::DepthFirst)
+            .map(|(n, depth)| (n.id().to_string(), depth))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            result,
+            vec![
+                ("2".to_string(), 0),
+                ("6".to_string(), 1),
+                ("10".to_string(), 2),
+                ("7".to_string(), 1),
+                ("8".to_string(), 1)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clone() -> Result<(), Box<dyn Error>> {
+        let ids = |iter: super::TreeIter| iter.map(|n| n.id().to_string()).collect::<Vec<_>>();
+
+        for (order, expected) in [
+            (
+                TraversalOrder::DepthFirst,
+                vec!["<ROOT>", "1", "4", "5", "2", "6", "7", "8", "3", "9"],
+            ),
+            (
+                TraversalOrder::BreadthFirst,
+                vec!["<ROOT>", "1", "2", "3", "4", "5", "6", "7", "8", "9"],
+            ),
+            (
+                TraversalOrder::PostOrder,
+                vec!["4", "5", "1", "6", "7", "8", "2", "9", "3", "<ROOT>"],
+            ),
+        ] {
+            let tree = setup_tree()?;
+            let mut iter = tree.traverse(order);
+            for _ in 0..4 {
+                iter.next();
+            }
+
+            let mut clone = iter.clone();
+            // Advancing the clone doesn't move the original
+            assert_eq!(clone.next().unwrap().id().to_string(), expected[4]);
+            assert_eq!(ids(iter.clone()), expected[4..]);
+            assert_eq!(ids(clone), expected[5..]);
+
+            // Changes to the tree after the clone don't show up in either iterator
+            let clone = iter.clone();
+            tree.create_child()?;
+            assert_eq!(ids(clone), expected[4..]);
+            assert_eq!(ids(iter), expected[4..]);
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_bfs() -> Result<(), Box<dyn Error>> {
         let tree = setup_tree()?;
@@ -168,6 +349,40 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_post_order() -> Result<(), Box<dyn Error>> {
+        let tree = setup_tree()?;
+        let result = tree
+            .traverse(TraversalOrder::PostOrder)
+            .map(|n| n.id().to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            result,
+            vec!["4", "5", "1", "6", "7", "8", "2", "9", "3", "<ROOT>"]
+        );
+
+        // Every node comes after all of its descendants
+        for (i, node) in tree.traverse(TraversalOrder::PostOrder).enumerate() {
+            for descendant in node.descendants(TraversalOrder::DepthFirst) {
+                let position = result
+                    .iter()
+                    .position(|id| id == &descendant.id().to_string())
+                    .unwrap();
+                assert!(position < i);
+            }
+        }
+
+        let node = tree.get_node("2").unwrap();
+        let result = node
+            .traverse(TraversalOrder::PostOrder)
+            .map(|n| n.id().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(result, vec!["6", "7", "8", "2"]);
+
+        Ok(())
+    }
+
     #[test]
     fn test_start_at() -> Result<(), Box<dyn Error>> {
         let tree = setup_tree()?;
@@ -1,17 +1,66 @@
 #![doc = include_str!("../README.md")]
 
+mod apply;
+mod conflict;
+mod diff;
 mod error;
 pub mod events;
+mod gc;
+mod hash;
+mod integrity;
 pub mod iter;
+mod modification;
 pub mod node;
+mod options;
+mod plain;
+mod readonly;
+mod repair;
+#[cfg(feature = "serde")]
+mod serialize;
+mod snapshot;
+mod stats;
+#[cfg(feature = "tokio")]
+mod stream;
+mod transaction;
 mod tree;
 mod tree_structure;
+mod undo;
+#[cfg(all(
+    feature = "wasm",
+    target_arch = "wasm32",
+    not(target_feature = "atomics")
+))]
+mod wasm;
 
+pub use apply::ApplyOutcome;
+pub use conflict::ConflictResolution;
+pub use diff::{TreeDiff, TreeEdit};
 pub use error::TreeError;
-pub use events::TreeEvent;
+pub use events::{
+    ChangeScope, DataChange, DataEvent, EventOrigin, EventPause, NodeEvent, TreeEvent,
+};
+pub use gc::GcStats;
+pub use integrity::IntegrityError;
 pub use iter::TraversalOrder;
-pub use node::{DeleteStrategy, Node, NodeApi, NodeId};
+pub use modification::Modification;
+pub use node::{DeleteStrategy, Node, NodeApi, NodeId, NodeRelationship, ParentMismatch};
+pub use options::{InsertTiebreak, OutlineOptions, PrettyPrintOptions, TreeOptions};
+pub use plain::PlainTree;
+pub use readonly::{ReadonlyNode, ReadonlyTree};
+pub use repair::{RepairAction, RepairReport};
+pub use snapshot::{NodeRow, SubtreeExport, TreeSnapshot};
+pub use stats::TreeStats;
+#[cfg(feature = "tokio")]
+pub use stream::TreeEventStream;
+pub use transaction::TreeTxn;
 pub use tree::Tree;
+pub use undo::TreeUndoManager;
+#[cfg(all(
+    feature = "wasm",
+    target_arch = "wasm32",
+    not(target_feature = "atomics")
+))]
+pub use wasm::{JsSubscription, JsTree};
 
 /// A convenience type alias for the result of tree operations.
 pub type Result<T> = std::result::Result<T, TreeError>;
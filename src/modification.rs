@@ -0,0 +1,74 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use yrs::{Any, Out};
+
+/// The most recent change to a node, as recorded by a tree created with
+/// [`TreeOptions::track_modifications`](crate::TreeOptions::track_modifications).
+/// See [`Node::last_modified`](crate::Node::last_modified).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Modification {
+    /// The ID of the Yrs client that made the change.
+    pub client_id: u64,
+    /// When the change was made, according to the clock of the client that made it,
+    /// with millisecond precision.
+    pub timestamp: SystemTime,
+}
+
+impl Modification {
+    pub(crate) fn now(client_id: u64) -> Self {
+        // The standard library has no clock on wasm32-unknown-unknown, where
+        // `SystemTime::now` panics, so the time comes from JavaScript when the `wasm`
+        // feature provides it, and is left at the Unix epoch otherwise
+        #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+        let millis = js_sys::Date::now() as u128;
+        #[cfg(all(not(feature = "wasm"), target_arch = "wasm32", target_os = "unknown"))]
+        let millis: u128 = 0;
+        #[cfg(not(any(
+            all(feature = "wasm", target_arch = "wasm32"),
+            all(target_arch = "wasm32", target_os = "unknown")
+        )))]
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        Self {
+            client_id,
+            timestamp: UNIX_EPOCH + Duration::from_millis(millis as u64),
+        }
+    }
+
+    /// Encodes the modification as the value stored in a node's container.
+    pub(crate) fn to_any(self) -> Any {
+        let millis = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        Any::Map(Arc::new(HashMap::from([
+            ("client".to_string(), Any::BigInt(self.client_id as i64)),
+            ("time".to_string(), Any::BigInt(millis as i64)),
+        ])))
+    }
+
+    /// Decodes the value stored in a node's container, returning `None` if it isn't
+    /// a modification.
+    pub(crate) fn from_out(value: Out) -> Option<Self> {
+        let Out::Any(Any::Map(map)) = value else {
+            return None;
+        };
+        let (Some(Any::BigInt(client_id)), Some(Any::BigInt(millis))) =
+            (map.get("client"), map.get("time"))
+        else {
+            return None;
+        };
+
+        Some(Self {
+            client_id: *client_id as u64,
+            timestamp: UNIX_EPOCH + Duration::from_millis(*millis as u64),
+        })
+    }
+}
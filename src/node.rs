@@ -1,18 +1,24 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
 use std::fmt;
+use std::str::FromStr;
 use std::sync::Arc;
 
-use uuid::Uuid;
 use yrs::block::Prelim;
 
 use crate::{
-    iter::{TraversalOrder, TreeIter},
-    Result, Tree, TreeError,
+    events::{ChangeScope, DataEvent, NodeEvent, Subscription},
+    iter::{TraversalOrder, TreeIter, TreeIterWithDepth},
+    modification::Modification,
+    snapshot::SubtreeExport,
+    PrettyPrintOptions, Result, Tree, TreeError,
 };
 
-/// The ID of a node in a tree. Strings can be made into `NodeId`s using the `into()` method,
-/// and `NodeId`s can be converted back into strings using the `to_string()` method.
+/// The ID of a node in a tree. Strings can be made into `NodeId`s using the `into()` or
+/// `parse()` methods, and `NodeId`s can be converted back into strings using the
+/// `to_string()` or `as_ref()` methods.
 ///
-/// Note that `Into<NodeId>` for the string `"<ROOT>"` will return `NodeId::Root`,
+/// Note that converting or parsing the string `"<ROOT>"` will return `NodeId::Root`,
 /// which cannot be used as a node ID as it is reserved for the actual root node of the tree.
 #[derive(Clone, Debug, Default, PartialOrd, Ord, PartialEq, Eq, Hash)]
 pub enum NodeId {
@@ -60,6 +66,23 @@ impl From<String> for NodeId {
     }
 }
 
+impl FromStr for NodeId {
+    type Err = Infallible;
+
+    fn from_str(id: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(NodeId::from(id))
+    }
+}
+
+impl AsRef<str> for NodeId {
+    fn as_ref(&self) -> &str {
+        match self {
+            NodeId::Root => "<ROOT>",
+            NodeId::Id(id) => id,
+        }
+    }
+}
+
 /// A trait for objects that can behave like a node in a tree;
 /// this is implemented for [`Node`] and [`Tree`]. When these methods
 /// are used on a [`Tree`], they behave as if they were called on the root node.
@@ -83,6 +106,37 @@ pub trait NodeApi {
         index: usize,
     ) -> Result<Arc<Node>>;
 
+    /// Creates a new child node with a generated ID and sets the given data on it, in
+    /// a single transaction.
+    ///
+    /// ```
+    /// # use std::{collections::HashMap, sync::Arc};
+    /// # use yrs_tree::{NodeApi, Tree};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let doc = Arc::new(yrs::Doc::new());
+    /// # let tree = Tree::new(doc, "test")?;
+    /// let node = tree.create_child_with_data(HashMap::from([
+    ///     ("name".to_string(), "Photos"),
+    ///     ("type".to_string(), "folder"),
+    /// ]))?;
+    /// assert_eq!(node.get_as::<String>("name")?, "Photos");
+    /// assert_eq!(node.get_as::<String>("type")?, "folder");
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn create_child_with_data(
+        self: &Arc<Self>,
+        data: HashMap<String, impl Prelim + Into<yrs::Any>>,
+    ) -> Result<Arc<Node>>;
+
+    /// Creates a new child node with the given ID and sets the given data on it, in a
+    /// single transaction.
+    fn create_child_with_id_and_data(
+        self: &Arc<Self>,
+        id: impl Into<NodeId>,
+        data: HashMap<String, impl Prelim + Into<yrs::Any>>,
+    ) -> Result<Arc<Node>>;
+
     /// Moves the node to the given parent, placing it in that parent's children at the given index.
     ///
     /// Given:
@@ -108,7 +162,24 @@ pub trait NodeApi {
     /// ```
     ///
     /// Passing `None` as the index moves the node to the end of the parent's children.
-    fn move_to(self: &Arc<Self>, parent: &Node, index: Option<usize>) -> Result<()>;
+    /// An index past the end of the parent's children returns
+    /// [`TreeError::IndexOutOfRange`].
+    ///
+    /// The parent can be given as a `&Arc<Node>`, as returned by the tree's methods, or
+    /// as a `&Node`.
+    fn move_to(self: &Arc<Self>, parent: impl AsRef<Node>, index: Option<usize>) -> Result<()>;
+
+    /// Checks whether the node could be moved to the given parent with [`NodeApi::move_to`],
+    /// without changing the tree. Returns the error that `move_to` would return:
+    ///
+    /// * [`TreeError::Cycle`] if `parent` is the node itself or one of its descendants
+    /// * [`TreeError::MissingParent`] if `parent` is not in the tree
+    /// * [`TreeError::UnsupportedOperation`] if the node is the root node
+    /// * [`TreeError::IndexOutOfRange`] if `index` is past the end of the parent's
+    ///   children
+    /// * [`TreeError::MaxDepthExceeded`] or [`TreeError::MaxChildrenExceeded`] if the
+    ///   move would exceed one of the tree's limits
+    fn can_move_to(self: &Arc<Self>, parent: impl AsRef<Node>, index: Option<usize>) -> Result<()>;
 
     /// Moves the node before the given node.
     ///
@@ -160,6 +231,213 @@ pub trait NodeApi {
     /// ```
     fn move_after(self: &Arc<Self>, other: &Arc<Node>) -> Result<()>;
 
+    /// Moves the node into `parent`, as its first child.
+    ///
+    /// Returns [`TreeError::Cycle`] if `parent` is the node itself or one of its
+    /// descendants, and [`TreeError::UnsupportedOperation`] if the node is the root node.
+    fn move_into_as_first_child(self: &Arc<Self>, parent: &Arc<Node>) -> Result<()>;
+
+    /// Moves the node into `parent`, as its last child.
+    ///
+    /// Returns [`TreeError::Cycle`] if `parent` is the node itself or one of its
+    /// descendants, and [`TreeError::UnsupportedOperation`] if the node is the root node.
+    fn move_into_as_last_child(self: &Arc<Self>, parent: &Arc<Node>) -> Result<()>;
+
+    /// Moves the node one position earlier among its siblings. Returns `false`, without
+    /// changing the tree, if the node is already its parent's first child.
+    ///
+    /// Returns [`TreeError::UnsupportedOperation`] if the node is the root node.
+    fn move_up(self: &Arc<Self>) -> Result<bool>;
+
+    /// Moves the node one position later among its siblings. Returns `false`, without
+    /// changing the tree, if the node is already its parent's last child.
+    ///
+    /// Returns [`TreeError::UnsupportedOperation`] if the node is the root node.
+    fn move_down(self: &Arc<Self>) -> Result<bool>;
+
+    /// Rearranges the node's children to match `order`, in a single transaction.
+    ///
+    /// Returns [`TreeError::InvalidId`] if `order` isn't a permutation of the node's
+    /// current children, i.e. if it's missing a child, lists one more than once, or
+    /// contains an ID that isn't a child of this node.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{NodeApi, Tree};
+    /// # let doc = Arc::new(yrs::Doc::new());
+    /// # let tree = Tree::new(doc, "test").unwrap();
+    /// let a = tree.create_child_with_id("A").unwrap();
+    /// let b = tree.create_child_with_id("B").unwrap();
+    /// let c = tree.create_child_with_id("C").unwrap();
+    ///
+    /// let order = [c.id().clone(), a.id().clone(), b.id().clone()];
+    /// tree.reorder_children(&order).unwrap();
+    /// assert_eq!(tree.children_ids(), order);
+    /// ```
+    fn reorder_children(self: &Arc<Self>, order: &[NodeId]) -> Result<()>;
+
+    /// Sorts the node's children with the comparator, in a single transaction. The
+    /// sort is stable, so children that compare equal keep their relative order.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{NodeApi, Tree};
+    /// # let doc = Arc::new(yrs::Doc::new());
+    /// # let tree = Tree::new(doc, "test").unwrap();
+    /// tree.create_child_with_id("B").unwrap();
+    /// tree.create_child_with_id("C").unwrap();
+    /// tree.create_child_with_id("A").unwrap();
+    ///
+    /// tree.sort_children_by(|a, b| a.id().cmp(b.id())).unwrap();
+    /// assert_eq!(tree.children_ids(), ["A", "B", "C"]);
+    /// ```
+    fn sort_children_by<F>(self: &Arc<Self>, mut cmp: F) -> Result<()>
+    where
+        F: FnMut(&Arc<Node>, &Arc<Node>) -> std::cmp::Ordering,
+    {
+        let mut children = self.children();
+        children.sort_by(&mut cmp);
+        let order = children
+            .iter()
+            .map(|child| child.id().clone())
+            .collect::<Vec<_>>();
+        self.reorder_children(&order)
+    }
+
+    /// Sorts the node's children by the key the function extracts from each, in a
+    /// single transaction. The key is extracted once per child, and the sort is
+    /// stable.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{NodeApi, Tree};
+    /// # let doc = Arc::new(yrs::Doc::new());
+    /// # let tree = Tree::new(doc, "test").unwrap();
+    /// tree.create_child_with_id("1").unwrap().set("name", "Photos").unwrap();
+    /// tree.create_child_with_id("2").unwrap().set("name", "Documents").unwrap();
+    ///
+    /// // Keep folders sorted by name
+    /// tree.sort_children_by_key(|node| node.get_as::<String>("name").ok()).unwrap();
+    /// assert_eq!(tree.children_ids(), ["2", "1"]);
+    /// ```
+    fn sort_children_by_key<K, F>(self: &Arc<Self>, key: F) -> Result<()>
+    where
+        K: Ord,
+        F: FnMut(&Arc<Node>) -> K,
+    {
+        let mut children = self.children();
+        children.sort_by_cached_key(key);
+        let order = children
+            .iter()
+            .map(|child| child.id().clone())
+            .collect::<Vec<_>>();
+        self.reorder_children(&order)
+    }
+
+    /// Swaps the positions of this node and `other`, including their subtrees.
+    ///
+    /// Given:
+    ///
+    /// ```text
+    /// <ROOT>
+    /// ├──A
+    /// │  ├──C
+    /// │  ├──D
+    /// │  └──E
+    /// └──B
+    /// ```
+    ///
+    /// If we call `C.swap_with(&E)`, we get:
+    ///
+    /// ```text
+    /// <ROOT>
+    /// ├──A
+    /// │  ├──E
+    /// │  ├──D
+    /// │  └──C
+    /// └──B
+    /// ```
+    ///
+    /// If the nodes have different parents, each node takes the other's parent and
+    /// position; calling `D.swap_with(&B)` on the original tree gives:
+    ///
+    /// ```text
+    /// <ROOT>
+    /// ├──A
+    /// │  ├──C
+    /// │  ├──B
+    /// │  └──E
+    /// └──D
+    /// ```
+    ///
+    /// Returns an error if either node is the root node, or if one node is an
+    /// ancestor of the other.
+    fn swap_with(self: &Arc<Self>, other: &Arc<Node>) -> Result<()>;
+
+    /// Creates a copy of the node at the end of `new_parent`'s children, with the given
+    /// ID or a generated one, and returns it. All of the node's data is copied in a
+    /// single transaction; the node's children are not. Shared types stored on the
+    /// node are copied as plain values.
+    ///
+    /// Returns [`TreeError::InvalidId`] if `new_id` is already in the tree, and
+    /// [`TreeError::UnsupportedOperation`] if the node is the root node.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{NodeApi, Tree};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let doc = Arc::new(yrs::Doc::new());
+    /// # let tree = Tree::new(doc, "test")?;
+    /// let template = tree.create_child()?;
+    /// template.set("name", "Untitled")?;
+    /// template.create_child()?;
+    ///
+    /// let folder = tree.create_child()?;
+    /// let copy = template.copy_to(&folder, Some("copy".into()))?;
+    /// assert_eq!(copy.get_as::<String>("name")?, "Untitled");
+    /// assert!(copy.children().is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn copy_to(
+        self: &Arc<Self>,
+        new_parent: impl AsRef<Node>,
+        new_id: Option<NodeId>,
+    ) -> Result<Arc<Node>>;
+
+    /// Creates a copy of the node and all of its descendants, with their data, at the
+    /// end of `new_parent`'s children, and returns the copy of the node. The copies
+    /// are created in a single transaction.
+    ///
+    /// Each copy gets the ID returned by `id_mapper` for the original's ID, or a
+    /// generated one if `id_mapper` is `None`. Returns [`TreeError::InvalidId`] without
+    /// changing the tree if a mapped ID is already in the tree, is `<ROOT>`, or is
+    /// returned for more than one node, and [`TreeError::UnsupportedOperation`] if the
+    /// node is the root node.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{NodeApi, NodeId, Tree};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let doc = Arc::new(yrs::Doc::new());
+    /// # let tree = Tree::new(doc, "test")?;
+    /// let template = tree.create_child_with_id("template")?;
+    /// template.create_child_with_id("readme")?;
+    ///
+    /// let project = tree.create_child_with_id("project")?;
+    /// let mapper = |id: &NodeId| NodeId::from(format!("project-{}", id));
+    /// let copy = template.clone_subtree_to(&project, Some(&mapper))?;
+    /// assert_eq!(copy.id(), &NodeId::from("project-template"));
+    /// assert_eq!(copy.children_ids(), vec!["project-readme"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn clone_subtree_to(
+        self: &Arc<Self>,
+        new_parent: impl AsRef<Node>,
+        id_mapper: Option<&dyn Fn(&NodeId) -> NodeId>,
+    ) -> Result<Arc<Node>>;
+
     /// Returns the parent of the node.
     fn parent(self: &Arc<Self>) -> Option<Arc<Node>>;
 
@@ -167,18 +445,169 @@ pub trait NodeApi {
     /// at the root node.
     fn ancestors(self: &Arc<Self>) -> Vec<Arc<Node>>;
 
+    /// Returns the node followed by its ancestors, ending at the root node. For the
+    /// root node itself, this is just the root node.
+    fn ancestors_including_self(self: &Arc<Self>) -> Vec<Arc<Node>>;
+
+    /// Returns the IDs of the node's ancestors, in the same order as
+    /// [`NodeApi::ancestors`], without creating a [`Node`] for each.
+    fn ancestor_ids(self: &Arc<Self>) -> Vec<NodeId>;
+
+    /// Returns the ancestor `n` levels above the node: the node itself for `0`, its
+    /// parent for `1`, its grandparent for `2`, and so on. Returns `None` if the node
+    /// has fewer than `n` ancestors.
+    fn nth_ancestor(self: &Arc<Self>, n: usize) -> Option<Arc<Node>>;
+
+    /// Returns the closest ancestor of the node for which `predicate` returns `true`,
+    /// or `None` if no ancestor matches. The root node is considered an ancestor.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{NodeApi, Tree};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let doc = Arc::new(yrs::Doc::new());
+    /// # let tree = Tree::new(doc, "test")?;
+    /// let section = tree.create_child()?;
+    /// section.set("type", "section")?;
+    /// let paragraph = section.create_child()?.create_child()?;
+    ///
+    /// let found = paragraph.nearest_ancestor_where(|n| {
+    ///     n.get_as::<String>("type").is_ok_and(|t| t == "section")
+    /// });
+    /// assert_eq!(found.unwrap().id(), section.id());
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn nearest_ancestor_where<F>(self: &Arc<Self>, predicate: F) -> Option<Arc<Node>>
+    where
+        F: Fn(&Arc<Node>) -> bool;
+
+    /// Returns the nodes visited when walking through the tree from this node to `other`:
+    /// up to their closest common ancestor, then down to `other`. Both nodes are
+    /// included, so the path from a node to itself is just that node.
+    ///
+    /// Given:
+    ///
+    /// ```text
+    /// <ROOT>
+    /// ├──A
+    /// │  ├──C
+    /// │  └──D
+    /// └──B
+    ///    └──E
+    /// ```
+    ///
+    /// `C.path_to(&E)` returns `[C, A, <ROOT>, B, E]`, and `A.path_to(&D)` returns
+    /// `[A, D]`.
+    ///
+    /// Returns `None` if the nodes belong to different trees, or if either node is
+    /// no longer in the tree.
+    fn path_to(self: &Arc<Self>, other: &Arc<Node>) -> Option<Vec<Arc<Node>>>;
+
     /// Returns the children of the node.
     fn children(self: &Arc<Self>) -> Vec<Arc<Node>>;
 
+    /// Returns the IDs of the node's children, in order, without creating a [`Node`]
+    /// for each.
+    fn children_ids(self: &Arc<Self>) -> Vec<NodeId>;
+
+    /// Returns the child at the given position among the node's children, or `None`
+    /// if `index` is out of bounds. Unlike indexing into [`NodeApi::children`], this
+    /// doesn't create a [`Node`] for every child.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{NodeApi, Tree};
+    /// # let doc = Arc::new(yrs::Doc::new());
+    /// # let tree = Tree::new(doc, "test").unwrap();
+    /// let a = tree.create_child_with_id("A").unwrap();
+    /// let b = tree.create_child_with_id("B").unwrap();
+    ///
+    /// assert_eq!(tree.get_child_at_index(1).unwrap().id(), b.id());
+    /// assert!(tree.get_child_at_index(2).is_none());
+    /// assert!(a.get_child_at_index(0).is_none());
+    /// ```
+    fn get_child_at_index(self: &Arc<Self>, index: usize) -> Option<Arc<Node>>;
+
+    /// Returns the number of children the node has, without collecting them.
+    fn get_child_count(self: &Arc<Self>) -> usize;
+
     /// Returns the descendants of the node. Equivalent to `self.traverse(order).skip(1).collect()`.
     fn descendants(self: &Arc<Self>, order: TraversalOrder) -> Vec<Arc<Node>>;
 
     /// Returns the siblings of the node.
     fn siblings(self: &Arc<Self>) -> Vec<Arc<Node>>;
 
+    /// Returns the position of the node among its parent's children, or `None` for
+    /// the root node.
+    fn index_in_parent(self: &Arc<Self>) -> Option<usize>;
+
+    /// Returns `true` if the node is the first of its parent's children. Always
+    /// `false` for the root node.
+    fn is_first_child(self: &Arc<Self>) -> bool;
+
+    /// Returns `true` if the node is the last of its parent's children. Always
+    /// `false` for the root node.
+    fn is_last_child(self: &Arc<Self>) -> bool;
+
+    /// Returns `true` if the node is its parent's only child. Always `false` for the
+    /// root node.
+    fn is_only_child(self: &Arc<Self>) -> bool;
+
     /// Returns an iterator over the node and its descendants in the given order.
     fn traverse(self: &Arc<Self>, order: TraversalOrder) -> TreeIter;
 
+    /// Returns an iterator over the node and its descendants in the given order, along
+    /// with the depth of each relative to this node: the node itself has a depth of 0,
+    /// its children 1, and so on. Unlike calling [`Node::depth`] on each node, this
+    /// doesn't walk up the tree for every node.
+    ///
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{NodeApi, TraversalOrder, Tree};
+    /// #
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let doc = Arc::new(yrs::Doc::new());
+    /// # let tree = Tree::new(doc, "test")?;
+    /// let a = tree.create_child_with_id("A")?;
+    /// a.create_child_with_id("C")?;
+    ///
+    /// let outline = tree
+    ///     .traverse_with_depth(TraversalOrder::DepthFirst)
+    ///     .map(|(node, depth)| format!("{}{}", "  ".repeat(depth), node.id()))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(outline, vec!["<ROOT>", "  A", "    C"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn traverse_with_depth(self: &Arc<Self>, order: TraversalOrder) -> TreeIterWithDepth {
+        self.traverse(order).with_depth()
+    }
+
+    /// Returns the first node in the subtree rooted at this node, including the node
+    /// itself, for which `predicate` returns `true`, searching in depth-first order.
+    fn subtree_find<F>(self: &Arc<Self>, predicate: F) -> Option<Arc<Node>>
+    where
+        F: Fn(&Arc<Node>) -> bool;
+
+    /// Returns all nodes in the subtree rooted at this node, including the node
+    /// itself, for which `predicate` returns `true`, in depth-first order.
+    fn subtree_find_all<F>(self: &Arc<Self>, predicate: F) -> Vec<Arc<Node>>
+    where
+        F: Fn(&Arc<Node>) -> bool;
+
+    /// Returns `true` if any node in the subtree rooted at this node, including the
+    /// node itself, satisfies `predicate`. Stops searching at the first match.
+    fn subtree_any<F>(self: &Arc<Self>, predicate: F) -> bool
+    where
+        F: Fn(&Arc<Node>) -> bool;
+
+    /// Returns `true` if every node in the subtree rooted at this node, including the
+    /// node itself, satisfies `predicate`. Stops searching at the first failure.
+    fn subtree_all<F>(self: &Arc<Self>, predicate: F) -> bool
+    where
+        F: Fn(&Arc<Node>) -> bool;
+
     /// Returns the depth of the node. The root node has a depth of 0; all other
     /// nodes have a depth of 1 plus the depth of their parent.
     fn depth(self: &Arc<Self>) -> usize;
@@ -202,13 +631,43 @@ pub enum DeleteStrategy {
     Cascade,
 }
 
+/// What [`Tree::get_or_create_node_with_id_using`](crate::Tree::get_or_create_node_with_id_using)
+/// does when the node already exists under a different parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParentMismatch {
+    /// Return the node where it is.
+    Keep,
+    /// Move the node to the end of the requested parent's children.
+    Move,
+    /// Return a [`TreeError::InvalidId`](crate::TreeError::InvalidId) error.
+    Error,
+}
+
+/// How one node is related to another, as returned by [`Node::relationship_to`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeRelationship {
+    /// Both are the same node.
+    Same,
+    /// The node is an ancestor of the other node.
+    Ancestor,
+    /// The node is a descendant of the other node.
+    Descendant,
+    /// The nodes have the same parent.
+    Sibling,
+    /// Neither node is an ancestor of the other, and they have different parents.
+    /// Holds the ID of their lowest common ancestor.
+    Cousin(NodeId),
+    /// The nodes are in different trees, or one of them isn't in the tree.
+    Unrelated,
+}
+
 /// A node in a tree.
 ///
 /// * See [`Tree`] for methods to create and find nodes in the tree.
 /// * See [`NodeApi`] for the operations that can be performed on a node.
 pub struct Node {
-    id: NodeId,
-    tree: Arc<Tree>,
+    pub(crate) id: NodeId,
+    pub(crate) tree: Arc<Tree>,
 }
 
 impl Node {
@@ -256,6 +715,36 @@ impl Node {
         Ok(())
     }
 
+    /// Renders the node and its descendants like [`Tree::pretty_print`], starting with
+    /// this node.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{NodeApi, Tree};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let doc = Arc::new(yrs::Doc::new());
+    /// # let tree = Tree::new(doc, "test")?;
+    /// let a = tree.create_child_with_id("A")?;
+    /// a.create_child_with_id("C")?;
+    /// a.create_child_with_id("D")?;
+    /// tree.create_child_with_id("B")?;
+    ///
+    /// assert_eq!(a.pretty_print(), "A\n├──C\n└──D\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pretty_print(&self) -> String {
+        self.pretty_print_with_options(&Default::default())
+    }
+
+    /// Renders the node and its descendants like [`Node::pretty_print`], with the
+    /// connectors and node labels chosen by the options.
+    pub fn pretty_print_with_options(&self, options: &PrettyPrintOptions) -> String {
+        let mut out = String::new();
+        let _ = self.tree.write_pretty(&mut out, &self.id, options, None);
+        out
+    }
+
     /// Sets a value on the node at the given key.
     ///
     /// See the "Implementors" section of the [`yrs::block::Prelim`] trait for more
@@ -336,43 +825,694 @@ impl Node {
     pub fn get_as<V: serde::de::DeserializeOwned>(&self, key: &str) -> Result<V> {
         self.tree.get_data_as(&self.id, key)
     }
-}
-
-impl NodeApi for Node {
-    fn id(self: &Arc<Self>) -> &NodeId {
-        &self.id
-    }
 
-    fn create_child(self: &Arc<Self>) -> Result<Arc<Self>> {
-        let id = Uuid::now_v7().to_string();
-        self.create_child_with_id(id)
+    /// Gets the value stored on the node at the given key, first storing the value
+    /// returned by `f` if there is none. Reading and storing happen in a single
+    /// transaction, so `f` is only called when the key is missing, and nothing else
+    /// can set the key in between.
+    ///
+    /// `f` runs while the transaction is open, so it can't use the tree.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{Tree, NodeApi};
+    /// #
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let doc = Arc::new(yrs::Doc::new());
+    /// # let tree = Tree::new(doc, "directory_structure")?;
+    /// let node = tree.create_child()?;
+    /// let name = node.get_or_insert_with("name", || "Untitled")?;
+    /// assert_eq!(name, yrs::Out::Any("Untitled".into()));
+    ///
+    /// // The existing value is kept
+    /// let name = node.get_or_insert_with("name", || "Other")?;
+    /// assert_eq!(name, yrs::Out::Any("Untitled".into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_or_insert_with<V: Prelim + Into<yrs::Any>>(
+        &self,
+        key: &str,
+        f: impl FnOnce() -> V,
+    ) -> Result<yrs::Out> {
+        self.tree.get_or_insert_data(&self.id, key, f)
     }
 
-    fn create_child_at(self: &Arc<Self>, index: usize) -> Result<Arc<Self>> {
-        let id = Uuid::now_v7().to_string();
-        self.do_create_child(id, Some(index))
+    /// Stores the value on the node at the given key only if there is no value there
+    /// yet, returning `true` if it was stored and `false` if the existing value was
+    /// kept. Checking and storing happen in a single transaction.
+    ///
+    /// This only sees the changes this client knows about: if two clients each store a
+    /// value at a key that's missing on both, both calls return `true`, and once their
+    /// changes are merged every client keeps the same one of the two values, just as
+    /// with concurrent calls to [`Node::set`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{Tree, NodeApi};
+    /// #
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let doc = Arc::new(yrs::Doc::new());
+    /// # let tree = Tree::new(doc, "directory_structure")?;
+    /// let node = tree.create_child()?;
+    /// assert!(node.set_if_absent("color", "blue")?);
+    /// assert!(!node.set_if_absent("color", "red")?);
+    /// assert_eq!(node.get_as::<String>("color")?, "blue");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_if_absent<V: Prelim + Into<yrs::Any>>(&self, key: &str, value: V) -> Result<bool> {
+        let mut stored = false;
+        self.tree.get_or_insert_data(&self.id, key, || {
+            stored = true;
+            value
+        })?;
+        Ok(stored)
     }
 
-    fn create_child_with_id(self: &Arc<Self>, id: impl Into<NodeId>) -> Result<Arc<Self>> {
-        self.do_create_child(id, None)
+    /// Returns the collaborative text stored on the node at the given key, first
+    /// storing an empty one if there is no value there, in a single transaction. The
+    /// text can be edited and observed with the methods of [`yrs::Text`] and
+    /// [`yrs::Observable`], using transactions on the tree's document; changes to it
+    /// are also reported by [`Node::observe_data`].
+    ///
+    /// Returns [`TreeError::UnsupportedOperation`] if the key holds a value that isn't
+    /// a text. As with [`Node::set_if_absent`], if two clients each store a text at a
+    /// key that's missing on both, every client keeps the same one of the two once
+    /// their changes are merged, along with the edits made to it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use yrs::{GetString, Text, Transact};
+    /// # use yrs_tree::{Tree, NodeApi};
+    /// #
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let doc = Arc::new(yrs::Doc::new());
+    /// # let tree = Tree::new(doc.clone(), "directory_structure")?;
+    /// let node = tree.create_child()?;
+    /// let body = node.text("body")?;
+    /// body.insert(&mut doc.transact_mut(), 0, "Hello, world");
+    ///
+    /// // The same text is returned for the key from then on
+    /// let body = node.text("body")?;
+    /// body.remove_range(&mut doc.transact_mut(), 5, 7);
+    /// assert_eq!(body.get_string(&doc.transact()), "Hello");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn text(&self, key: &str) -> Result<yrs::TextRef> {
+        match self
+            .tree
+            .get_or_insert_data(&self.id, key, || yrs::TextPrelim::new(""))?
+        {
+            yrs::Out::YText(text) => Ok(text),
+            _ => Err(TreeError::UnsupportedOperation(format!(
+                "The value at key {} on node {} is not a text",
+                key, self.id
+            ))),
+        }
     }
 
-    fn create_child_with_id_at(
-        self: &Arc<Self>,
-        id: impl Into<NodeId>,
-        index: usize,
-    ) -> Result<Arc<Self>> {
-        self.do_create_child(id, Some(index))
+    /// Returns the collaborative array stored on the node at the given key, first
+    /// storing an empty one if there is no value there, in a single transaction. The
+    /// array can be edited and observed with the methods of [`yrs::Array`] and
+    /// [`yrs::Observable`], using transactions on the tree's document; changes to it
+    /// are also reported by [`Node::observe_data`].
+    ///
+    /// The array is an ordinary value of the key as far as the other data methods
+    /// are concerned: [`Node::get`] returns it as a [`yrs::Out::YArray`],
+    /// [`Node::get_as`] reads its current contents, and [`Node::set`] replaces it
+    /// with the new value, after which this method returns an error for the key.
+    ///
+    /// Returns [`TreeError::UnsupportedOperation`] if the key holds a value that isn't
+    /// an array. As with [`Node::text`], if two clients each store an array at a key
+    /// that's missing on both, every client keeps the same one of the two once their
+    /// changes are merged, along with the edits made to it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use yrs::{Array, Transact};
+    /// # use yrs_tree::{Tree, NodeApi};
+    /// #
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let doc = Arc::new(yrs::Doc::new());
+    /// # let tree = Tree::new(doc.clone(), "directory_structure")?;
+    /// let node = tree.create_child()?;
+    /// let tags = node.array("tags")?;
+    /// tags.push_back(&mut doc.transact_mut(), "draft");
+    /// node.array("tags")?.push_back(&mut doc.transact_mut(), "urgent");
+    ///
+    /// assert_eq!(node.get_as::<Vec<String>>("tags")?, vec!["draft", "urgent"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn array(&self, key: &str) -> Result<yrs::ArrayRef> {
+        match self
+            .tree
+            .get_or_insert_data(&self.id, key, yrs::ArrayPrelim::default)?
+        {
+            yrs::Out::YArray(array) => Ok(array),
+            _ => Err(TreeError::UnsupportedOperation(format!(
+                "The value at key {} on node {} is not an array",
+                key, self.id
+            ))),
+        }
     }
 
-    fn children(self: &Arc<Self>) -> Vec<Arc<Self>> {
-        self.tree
+    /// Returns the client ID and time of the last change to this node's position or
+    /// data, if it was made by a tree created with
+    /// [`TreeOptions::track_modifications`](crate::TreeOptions::track_modifications).
+    /// Returns `None` if no change has been recorded, or if the Yrs document can't be
+    /// read, e.g. from inside an event callback.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{NodeApi, Tree, TreeOptions};
+    /// #
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let doc = Arc::new(yrs::Doc::new());
+    /// let options = TreeOptions {
+    ///     track_modifications: true,
+    ///     ..Default::default()
+    /// };
+    /// let tree = Tree::new_with_options(doc.clone(), "test", options)?;
+    ///
+    /// let node = tree.create_child()?;
+    /// node.set("name", "README.md")?;
+    /// assert_eq!(node.last_modified().unwrap().client_id, doc.client_id());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn last_modified(&self) -> Option<Modification> {
+        self.tree.last_modified(&self.id)
+    }
+
+    /// Serializes `value` and stores it on the node at the given key. Unlike [`Node::set`],
+    /// this accepts any [`serde::Serialize`] type; structs and maps are stored as a
+    /// single nested value that can be read back with [`Node::get_json`].
+    ///
+    /// Returns [`TreeError::SerializationFailed`] if the value can't be represented
+    /// as a Yrs value, e.g. a map with non-string keys.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{Tree, NodeApi};
+    /// # use serde::{Deserialize, Serialize};
+    /// #
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let doc = Arc::new(yrs::Doc::new());
+    /// # let tree = Tree::new(doc, "directory_structure")?;
+    /// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    /// struct File {
+    ///     name: String,
+    ///     tags: Vec<String>,
+    /// }
+    ///
+    /// let node = tree.create_child()?;
+    /// let file = File {
+    ///     name: "README.md".to_string(),
+    ///     tags: vec!["docs".to_string()],
+    /// };
+    /// node.set_json("file", &file)?;
+    /// assert_eq!(node.get_json::<File>("file")?, Some(file));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_json<T: serde::Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        let any = yrs::encoding::serde::to_any(value)
+            .map_err(|e| TreeError::SerializationFailed(e.to_string()))?;
+        self.set(key, any)?;
+        Ok(())
+    }
+
+    /// Gets a value stored with [`Node::set_json`] (or [`Node::set`]) at the given key,
+    /// deserialized into `T`. Returns `None` if no value was found.
+    pub fn get_json<T: serde::de::DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        if self.get(key)?.is_none() {
+            return Ok(None);
+        }
+
+        self.get_as(key).map(Some)
+    }
+
+    /// Captures this node and all of its descendants, along with their data, so
+    /// they can be recreated elsewhere with [`Node::import_subtree`], including in a
+    /// tree in another document.
+    ///
+    /// Exporting the root node captures it as a node without data, so importing it
+    /// creates a new node holding copies of the root's children.
+    pub fn export_subtree(&self) -> SubtreeExport {
+        SubtreeExport {
+            nodes: self.tree.capture_subtree(&self.id),
+        }
+    }
+
+    /// Recreates an exported subtree as a child of this node at the given index,
+    /// returning the new copy of the exported node. Passing `None` as the index
+    /// places it after this node's existing children.
+    ///
+    /// Every imported node is given a newly generated ID, so the same export can be
+    /// imported any number of times; use [`Node::import_subtree_preserving_ids`] to
+    /// keep the original IDs instead. The nodes are created in a single transaction.
+    ///
+    /// Returns [`TreeError::InvalidExport`] without changing the tree if the export,
+    /// for example one deserialized from untrusted input, doesn't describe a single
+    /// subtree.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{NodeApi, Tree};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let tree1 = Tree::new(Arc::new(yrs::Doc::new()), "test")?;
+    /// # let tree2 = Tree::new(Arc::new(yrs::Doc::new()), "test")?;
+    /// let folder = tree1.create_child()?;
+    /// folder.set("name", "Documents")?;
+    /// folder.create_child()?;
+    ///
+    /// let copy = tree2.root().import_subtree(&folder.export_subtree(), None)?;
+    /// assert_ne!(copy.id(), folder.id());
+    /// assert_eq!(copy.get_as::<String>("name")?, "Documents");
+    /// assert_eq!(copy.children().len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn import_subtree(
+        &self,
+        export: &SubtreeExport,
+        index: Option<usize>,
+    ) -> Result<Arc<Node>> {
+        self.do_import_subtree(export, index, false)
+    }
+
+    /// Like [`Node::import_subtree`], but creates the nodes with the IDs they had
+    /// when they were exported.
+    ///
+    /// Returns [`TreeError::InvalidId`] without changing the tree if any of the
+    /// IDs is already used in this tree, or if the export is of the root node.
+    pub fn import_subtree_preserving_ids(
+        &self,
+        export: &SubtreeExport,
+        index: Option<usize>,
+    ) -> Result<Arc<Node>> {
+        self.do_import_subtree(export, index, true)
+    }
+
+    /// Removes this node and its descendants from the tree, returning them as an
+    /// export that can be inserted elsewhere with [`Node::attach`] or
+    /// [`Node::import_subtree`], including in a tree in another document.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{NodeApi, Tree};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let tree1 = Tree::new(Arc::new(yrs::Doc::new()), "test")?;
+    /// # let tree2 = Tree::new(Arc::new(yrs::Doc::new()), "test")?;
+    /// let folder = tree1.create_child()?;
+    /// folder.create_child()?;
+    ///
+    /// let cut = folder.detach()?;
+    /// assert!(tree1.children().is_empty());
+    ///
+    /// let pasted = tree2.root().attach(&cut)?;
+    /// assert_eq!(pasted.children().len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn detach(self: &Arc<Self>) -> Result<SubtreeExport> {
+        if self.id == NodeId::Root {
+            return Err(TreeError::InvalidTarget(NodeId::Root));
+        }
+
+        let export = self.export_subtree();
+        self.delete(DeleteStrategy::Cascade)?;
+        Ok(export)
+    }
+
+    /// Inserts an exported subtree after this node's existing children, giving the
+    /// nodes new IDs; see [`Node::import_subtree`].
+    pub fn attach(self: &Arc<Self>, subtree: &SubtreeExport) -> Result<Arc<Node>> {
+        self.import_subtree(subtree, None)
+    }
+
+    /// Replaces this node with a new node with the given ID, at the same position
+    /// under the same parent, returning the new node. The node's children are moved
+    /// to the new node in order, and then the node is deleted. The new node starts
+    /// without data; the node's data is deleted along with it.
+    ///
+    /// The changes are made in a single transaction, so other clients receive them as
+    /// one update and they are undone as a single step. Returns
+    /// [`TreeError::InvalidTarget`] for the root node or a node that is no longer in
+    /// the tree, and [`TreeError::InvalidId`] if `new_id` is `<ROOT>` or is already
+    /// used in the tree.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{NodeApi, Tree};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let tree = Tree::new(Arc::new(yrs::Doc::new()), "test")?;
+    /// tree.create_child_with_id("before")?;
+    /// let item = tree.create_child_with_id("item")?;
+    /// item.create_child_with_id("child")?;
+    /// tree.create_child_with_id("after")?;
+    ///
+    /// let task = item.replace_with("task")?;
+    /// assert_eq!(task.index_in_parent(), Some(1));
+    /// assert_eq!(*task.children()[0].id(), "child");
+    /// assert!(!tree.has_node("item"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn replace_with(self: &Arc<Self>, new_id: impl Into<NodeId>) -> Result<Arc<Node>> {
+        let new_id = new_id.into();
+        let Some(parent) = self.tree.get_parent(&self.id) else {
+            return Err(TreeError::InvalidTarget(self.id.clone()));
+        };
+        if new_id == NodeId::Root {
+            return Err(TreeError::InvalidId(
+                "<ROOT> cannot be used as a node ID".to_string(),
+            ));
+        }
+        if self.tree.has_node(new_id.clone()) {
+            return Err(TreeError::InvalidId(format!(
+                "Node {} already exists in the tree",
+                new_id
+            )));
+        }
+
+        self.tree.transact(|txn| {
+            let index = txn.children(&parent).iter().position(|id| *id == self.id);
+            txn.create_child_with_id(&parent, new_id.clone())?;
+            txn.move_nodes(std::slice::from_ref(&new_id), &parent, index)?;
+
+            let children = txn.children(&self.id);
+            if !children.is_empty() {
+                txn.move_nodes(&children, &new_id, None)?;
+            }
+            txn.delete(&self.id, DeleteStrategy::Cascade)
+        })?;
+
+        Ok(Node::new(new_id, self.tree.clone()))
+    }
+
+    fn do_import_subtree(
+        &self,
+        export: &SubtreeExport,
+        index: Option<usize>,
+        preserve_ids: bool,
+    ) -> Result<Arc<Node>> {
+        export.validate()?;
+
+        let new_ids = export
+            .nodes
+            .iter()
+            .map(|node| {
+                let id = if preserve_ids {
+                    node.id.clone()
+                } else {
+                    NodeId::from(self.tree.generate_id()?)
+                };
+                Ok((node.id.clone(), id))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        if preserve_ids {
+            self.check_new_ids(export, &new_ids)?;
+        }
+
+        self.import_with_ids(export, index, &new_ids)
+    }
+
+    /// Checks that the IDs chosen for the nodes of an export can be created in this
+    /// node's tree. This is done up front, since the transaction keeps the nodes
+    /// created before an error.
+    fn check_new_ids(
+        &self,
+        export: &SubtreeExport,
+        new_ids: &HashMap<NodeId, NodeId>,
+    ) -> Result<()> {
+        let mut seen = HashSet::new();
+        for node in &export.nodes {
+            let id = &new_ids[&node.id];
+            if *id == NodeId::Root {
+                return Err(TreeError::InvalidId(
+                    "<ROOT> cannot be used as a node ID".to_string(),
+                ));
+            }
+            if self.tree.get_parent(id).is_some() || !seen.insert(id) {
+                return Err(TreeError::InvalidId(format!(
+                    "Node {} already exists in the tree",
+                    id
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates the nodes of an export under this node in a single transaction, giving
+    /// each node the ID it's mapped to in `new_ids`.
+    fn import_with_ids(
+        &self,
+        export: &SubtreeExport,
+        index: Option<usize>,
+        new_ids: &HashMap<NodeId, NodeId>,
+    ) -> Result<Arc<Node>> {
+        self.tree.transact(|txn| {
+            for (i, node) in export.nodes.iter().enumerate() {
+                let id = new_ids[&node.id].clone();
+                if i == 0 {
+                    txn.create_child_with_id_at(&self.id, id.clone(), index)?;
+                } else {
+                    txn.create_child_with_id(&new_ids[&node.parent], id.clone())?;
+                }
+                for (key, value) in &node.data {
+                    txn.set(&id, key, value.clone())?;
+                }
+            }
+
+            Ok(())
+        })?;
+
+        Ok(Node::new(
+            new_ids[&export.nodes[0].id].clone(),
+            self.tree.clone(),
+        ))
+    }
+
+    /// Returns how this node is related to `other`, which answers whether they're
+    /// the same node, whether one contains the other, and whether they share a parent
+    /// or only a more distant ancestor, with a single lookup in the tree.
+    ///
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{NodeApi, NodeRelationship, Tree};
+    /// #
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let doc = Arc::new(yrs::Doc::new());
+    /// # let tree = Tree::new(doc, "test")?;
+    /// let a = tree.create_child_with_id("A")?;
+    /// let b = tree.create_child_with_id("B")?;
+    /// let c = a.create_child_with_id("C")?;
+    /// let d = b.create_child_with_id("D")?;
+    ///
+    /// assert_eq!(a.relationship_to(&c), NodeRelationship::Ancestor);
+    /// assert_eq!(a.relationship_to(&b), NodeRelationship::Sibling);
+    /// assert_eq!(c.relationship_to(&d), NodeRelationship::Cousin(tree.id().clone()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn relationship_to(self: &Arc<Self>, other: &Arc<Node>) -> NodeRelationship {
+        if !Arc::ptr_eq(&self.tree, &other.tree)
+            || !self.tree.has_node(self.id.clone())
+            || !self.tree.has_node(other.id.clone())
+        {
+            return NodeRelationship::Unrelated;
+        }
+
+        if self.id == other.id {
+            return NodeRelationship::Same;
+        }
+
+        let lock = self.tree.structure.lock();
+        let structure = lock.borrow();
+        let common = structure.lowest_common_ancestor(&self.id, &other.id);
+        if common == self.id {
+            NodeRelationship::Ancestor
+        } else if common == other.id {
+            NodeRelationship::Descendant
+        } else if structure.get_parent(&self.id) == structure.get_parent(&other.id) {
+            NodeRelationship::Sibling
+        } else {
+            NodeRelationship::Cousin(common)
+        }
+    }
+
+    /// Returns a subscription to changes affecting this node. When dropped, the
+    /// subscription is automatically cancelled.
+    ///
+    /// With [`ChangeScope::Node`], the callback fires when this node is moved or
+    /// deleted, or when its data changes. With [`ChangeScope::Subtree`], it also fires
+    /// when any descendant is created, deleted, moved, or has its data changed, and
+    /// when a node is moved into or out of the subtree.
+    ///
+    /// The subscription follows the node's ID, so it survives the node being moved;
+    /// it is cancelled automatically once the node is deleted.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{ChangeScope, NodeApi, NodeEvent, Tree};
+    /// # use yrs::Doc;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let doc = Arc::new(Doc::new());
+    /// # let tree = Tree::new(doc, "directory_structure")?;
+    /// let folder = tree.create_child_with_id("folder")?;
+    /// let _sub = folder.on_change(ChangeScope::Subtree, |e| match e {
+    ///     NodeEvent::Created(id) => println!("{} was added", id),
+    ///     _ => {}
+    /// });
+    /// folder.create_child_with_id("file")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_change(
+        &self,
+        scope: ChangeScope,
+        callback: impl Fn(&NodeEvent) + Send + Sync + 'static,
+    ) -> Subscription {
+        self.tree.on_node_change(&self.id, scope, callback)
+    }
+
+    /// Calls `callback` with the changed keys, and their old and new values, whenever
+    /// the data stored on this node changes, whether locally or from a remote update.
+    /// Returns a subscription that cancels the callback when dropped; it is also
+    /// cancelled automatically once the node is deleted. Changes made inside shared
+    /// types stored on the node are included; see [`Tree::observe_data_deep`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{NodeApi, Tree};
+    /// # use yrs::Doc;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let doc = Arc::new(Doc::new());
+    /// # let tree = Tree::new(doc, "directory_structure")?;
+    /// let file = tree.create_child_with_id("file")?;
+    /// let _sub = file.observe_data(|e| {
+    ///     for change in &e.changes {
+    ///         println!("{}: {:?} -> {:?}", change.key, change.old_value, change.new_value);
+    ///     }
+    /// });
+    /// file.set("name", "README.md")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn observe_data(
+        &self,
+        callback: impl Fn(&DataEvent) + Send + Sync + 'static,
+    ) -> Subscription {
+        self.tree.on_node_data_change(&self.id, callback)
+    }
+}
+
+impl NodeApi for Node {
+    fn id(self: &Arc<Self>) -> &NodeId {
+        &self.id
+    }
+
+    fn create_child(self: &Arc<Self>) -> Result<Arc<Self>> {
+        let id = self
+            .tree
+            .generate_child_id(|id| self.tree.has_node(id.clone()))?;
+        self.create_child_with_id(id)
+    }
+
+    fn create_child_at(self: &Arc<Self>, index: usize) -> Result<Arc<Self>> {
+        let id = self
+            .tree
+            .generate_child_id(|id| self.tree.has_node(id.clone()))?;
+        self.do_create_child(id, Some(index))
+    }
+
+    fn create_child_with_id(self: &Arc<Self>, id: impl Into<NodeId>) -> Result<Arc<Self>> {
+        self.do_create_child(id, None)
+    }
+
+    fn create_child_with_id_at(
+        self: &Arc<Self>,
+        id: impl Into<NodeId>,
+        index: usize,
+    ) -> Result<Arc<Self>> {
+        self.do_create_child(id, Some(index))
+    }
+
+    fn create_child_with_data(
+        self: &Arc<Self>,
+        data: HashMap<String, impl Prelim + Into<yrs::Any>>,
+    ) -> Result<Arc<Self>> {
+        let id = self.tree.transact(|txn| {
+            let id = txn.create_child(&self.id)?;
+            for (key, value) in data {
+                txn.set(&id, &key, value)?;
+            }
+            Ok(id)
+        })?;
+        Ok(Self::new(id, self.tree.clone()))
+    }
+
+    fn create_child_with_id_and_data(
+        self: &Arc<Self>,
+        id: impl Into<NodeId>,
+        data: HashMap<String, impl Prelim + Into<yrs::Any>>,
+    ) -> Result<Arc<Self>> {
+        let id = self.tree.transact(|txn| {
+            let id = txn.create_child_with_id(&self.id, id)?;
+            for (key, value) in data {
+                txn.set(&id, &key, value)?;
+            }
+            Ok(id)
+        })?;
+        Ok(Self::new(id, self.tree.clone()))
+    }
+
+    fn children(self: &Arc<Self>) -> Vec<Arc<Self>> {
+        self.tree
             .get_children(&self.id)
             .into_iter()
             .map(|id| Node::new(id.clone(), self.tree.clone()))
             .collect()
     }
 
+    fn children_ids(self: &Arc<Self>) -> Vec<NodeId> {
+        self.tree.get_children(&self.id)
+    }
+
+    fn get_child_at_index(self: &Arc<Self>, index: usize) -> Option<Arc<Self>> {
+        self.tree
+            .child_at_index(&self.id, index)
+            .map(|id| Node::new(id, self.tree.clone()))
+    }
+
+    fn get_child_count(self: &Arc<Self>) -> usize {
+        self.tree.child_count(&self.id)
+    }
+
     fn descendants(self: &Arc<Self>, order: TraversalOrder) -> Vec<Arc<Self>> {
         // Don't list ourselves as a descendant
         self.traverse(order).skip(1).collect()
@@ -396,6 +1536,62 @@ impl NodeApi for Node {
         ancestors
     }
 
+    fn ancestors_including_self(self: &Arc<Self>) -> Vec<Arc<Self>> {
+        let mut ancestors = vec![self.clone()];
+        ancestors.extend(self.ancestors());
+        ancestors
+    }
+
+    fn ancestor_ids(self: &Arc<Self>) -> Vec<NodeId> {
+        self.tree.get_ancestors(&self.id)
+    }
+
+    fn nth_ancestor(self: &Arc<Self>, n: usize) -> Option<Arc<Self>> {
+        let mut id = self.id.clone();
+        for _ in 0..n {
+            id = self.tree.get_parent(&id)?;
+        }
+
+        Some(Node::new(id, self.tree.clone()))
+    }
+
+    fn nearest_ancestor_where<F>(self: &Arc<Self>, predicate: F) -> Option<Arc<Self>>
+    where
+        F: Fn(&Arc<Self>) -> bool,
+    {
+        let mut current = self.parent();
+
+        while let Some(parent) = current {
+            if predicate(&parent) {
+                return Some(parent);
+            }
+            current = parent.parent();
+        }
+
+        None
+    }
+
+    fn path_to(self: &Arc<Self>, other: &Arc<Node>) -> Option<Vec<Arc<Node>>> {
+        if !Arc::ptr_eq(&self.tree, &other.tree)
+            || !self.tree.has_node(self.id.clone())
+            || !self.tree.has_node(other.id.clone())
+        {
+            return None;
+        }
+
+        let common = self.tree.lowest_common_ancestor(self, other);
+
+        let mut up = self.ancestors_including_self();
+        let up_index = up.iter().position(|n| n.id == common.id)?;
+        let mut down = other.ancestors_including_self();
+        let down_index = down.iter().position(|n| n.id == common.id)?;
+
+        up.truncate(up_index + 1);
+        down.truncate(down_index);
+        up.extend(down.into_iter().rev());
+        Some(up)
+    }
+
     fn siblings(self: &Arc<Self>) -> Vec<Arc<Self>> {
         if let Some(parent) = self.parent() {
             parent.children().clone()
@@ -404,10 +1600,63 @@ impl NodeApi for Node {
         }
     }
 
+    fn index_in_parent(self: &Arc<Self>) -> Option<usize> {
+        let parent = self.tree.get_parent(&self.id)?;
+        self.tree
+            .get_children(&parent)
+            .iter()
+            .position(|child| child == &self.id)
+    }
+
+    fn is_first_child(self: &Arc<Self>) -> bool {
+        matches!(self.tree.sibling_position(&self.id), Some((0, _)))
+    }
+
+    fn is_last_child(self: &Arc<Self>) -> bool {
+        matches!(self.tree.sibling_position(&self.id), Some((index, count)) if index + 1 == count)
+    }
+
+    fn is_only_child(self: &Arc<Self>) -> bool {
+        matches!(self.tree.sibling_position(&self.id), Some((_, 1)))
+    }
+
     fn traverse(self: &Arc<Self>, order: TraversalOrder) -> TreeIter {
         self.tree.traverse_starting_at(self.id(), order)
     }
 
+    fn subtree_find<F>(self: &Arc<Self>, predicate: F) -> Option<Arc<Self>>
+    where
+        F: Fn(&Arc<Self>) -> bool,
+    {
+        self.traverse(TraversalOrder::DepthFirst)
+            .find(|node| predicate(node))
+    }
+
+    fn subtree_find_all<F>(self: &Arc<Self>, predicate: F) -> Vec<Arc<Self>>
+    where
+        F: Fn(&Arc<Self>) -> bool,
+    {
+        self.traverse(TraversalOrder::DepthFirst)
+            .filter(|node| predicate(node))
+            .collect()
+    }
+
+    fn subtree_any<F>(self: &Arc<Self>, predicate: F) -> bool
+    where
+        F: Fn(&Arc<Self>) -> bool,
+    {
+        self.traverse(TraversalOrder::DepthFirst)
+            .any(|node| predicate(&node))
+    }
+
+    fn subtree_all<F>(self: &Arc<Self>, predicate: F) -> bool
+    where
+        F: Fn(&Arc<Self>) -> bool,
+    {
+        self.traverse(TraversalOrder::DepthFirst)
+            .all(|node| predicate(&node))
+    }
+
     fn depth(self: &Arc<Self>) -> usize {
         if self.id == NodeId::Root {
             return 0;
@@ -426,8 +1675,13 @@ impl NodeApi for Node {
         depth
     }
 
-    fn move_to(self: &Arc<Self>, parent: &Node, index: Option<usize>) -> Result<()> {
-        self.tree.update_node(&self.id, &parent.id, index)
+    fn move_to(self: &Arc<Self>, parent: impl AsRef<Node>, index: Option<usize>) -> Result<()> {
+        self.tree.move_node(&self.id, &parent.as_ref().id, index)
+    }
+
+    fn can_move_to(self: &Arc<Self>, parent: impl AsRef<Node>, index: Option<usize>) -> Result<()> {
+        self.tree
+            .validate_move(&self.id, &parent.as_ref().id, index)
     }
 
     fn move_before(self: &Arc<Self>, other: &Arc<Node>) -> Result<()> {
@@ -438,11 +1692,168 @@ impl NodeApi for Node {
         self.move_relative(other, 1)
     }
 
+    fn move_into_as_first_child(self: &Arc<Self>, parent: &Arc<Node>) -> Result<()> {
+        if self.id == NodeId::Root {
+            return Err(TreeError::UnsupportedOperation(
+                "Cannot move the root node".to_string(),
+            ));
+        }
+
+        self.tree
+            .move_many(std::slice::from_ref(&self.id), parent, Some(0))
+    }
+
+    fn move_into_as_last_child(self: &Arc<Self>, parent: &Arc<Node>) -> Result<()> {
+        self.move_to(parent, None)
+    }
+
+    fn move_up(self: &Arc<Self>) -> Result<bool> {
+        let (Some(parent), Some(index)) = (self.parent(), self.index_in_parent()) else {
+            return Err(TreeError::UnsupportedOperation(
+                "Cannot move the root node".to_string(),
+            ));
+        };
+
+        if index == 0 {
+            return Ok(false);
+        }
+
+        // The index passed to `move_many` skips the moved node itself, so a node
+        // previously at `index` ends up between the siblings at `index - 2` and `index - 1`
+        self.tree
+            .move_many(std::slice::from_ref(&self.id), &parent, Some(index - 1))?;
+        Ok(true)
+    }
+
+    fn move_down(self: &Arc<Self>) -> Result<bool> {
+        let (Some(parent), Some(index)) = (self.parent(), self.index_in_parent()) else {
+            return Err(TreeError::UnsupportedOperation(
+                "Cannot move the root node".to_string(),
+            ));
+        };
+
+        if index + 1 >= parent.children().len() {
+            return Ok(false);
+        }
+
+        self.tree
+            .move_many(std::slice::from_ref(&self.id), &parent, Some(index + 1))?;
+        Ok(true)
+    }
+
+    fn reorder_children(self: &Arc<Self>, order: &[NodeId]) -> Result<()> {
+        let children = self.children_ids();
+        let mut seen = HashSet::new();
+        for id in order {
+            if !children.contains(id) {
+                return Err(TreeError::InvalidId(format!(
+                    "Node {} is not a child of {}",
+                    id, self.id
+                )));
+            }
+            if !seen.insert(id) {
+                return Err(TreeError::InvalidId(format!(
+                    "Node {} appears more than once in the new order",
+                    id
+                )));
+            }
+        }
+        if let Some(missing) = children.iter().find(|id| !seen.contains(id)) {
+            return Err(TreeError::InvalidId(format!(
+                "Node {} is missing from the new order",
+                missing
+            )));
+        }
+
+        if children == order {
+            return Ok(());
+        }
+
+        self.tree.move_many(order, self, Some(0))
+    }
+
+    fn swap_with(self: &Arc<Self>, other: &Arc<Node>) -> Result<()> {
+        self.tree.swap_nodes(&self.id, &other.id)
+    }
+
+    fn copy_to(
+        self: &Arc<Self>,
+        new_parent: impl AsRef<Node>,
+        new_id: Option<NodeId>,
+    ) -> Result<Arc<Node>> {
+        if self.id == NodeId::Root {
+            return Err(TreeError::UnsupportedOperation(
+                "Cannot copy the root node".to_string(),
+            ));
+        }
+
+        let new_id = match new_id {
+            Some(new_id) => new_id,
+            None => self.tree.generate_id()?.into(),
+        };
+        if self.tree.has_node(new_id.clone()) {
+            return Err(TreeError::InvalidId(format!(
+                "Node {} already exists in the tree",
+                new_id
+            )));
+        }
+
+        let parent = new_parent.as_ref().id.clone();
+        let data = self.tree.get_all_data(&self.id)?;
+        self.tree.transact(|txn| {
+            txn.create_child_with_id(&parent, new_id.clone())?;
+            for (key, value) in data {
+                txn.set(&new_id, &key, value)?;
+            }
+            Ok(())
+        })?;
+
+        Ok(Node::new(new_id, self.tree.clone()))
+    }
+
+    fn clone_subtree_to(
+        self: &Arc<Self>,
+        new_parent: impl AsRef<Node>,
+        id_mapper: Option<&dyn Fn(&NodeId) -> NodeId>,
+    ) -> Result<Arc<Node>> {
+        if self.id == NodeId::Root {
+            return Err(TreeError::UnsupportedOperation(
+                "Cannot clone the root node".to_string(),
+            ));
+        }
+
+        let new_parent = new_parent.as_ref();
+        let export = self.export_subtree();
+        let new_ids = export
+            .nodes
+            .iter()
+            .map(|node| {
+                let id = match id_mapper {
+                    Some(mapper) => mapper(&node.id),
+                    None => NodeId::from(new_parent.tree.generate_id()?),
+                };
+                Ok((node.id.clone(), id))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        if id_mapper.is_some() {
+            new_parent.check_new_ids(&export, &new_ids)?;
+        }
+
+        new_parent.import_with_ids(&export, None, &new_ids)
+    }
+
     fn delete(self: &Arc<Self>, strategy: DeleteStrategy) -> Result<()> {
         self.tree.delete_node(&self.id, strategy)
     }
 }
 
+impl AsRef<Node> for Node {
+    fn as_ref(&self) -> &Node {
+        self
+    }
+}
+
 impl fmt::Debug for Node {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Node({})", self.id)
@@ -0,0 +1,218 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use crate::Node;
+
+/// Options for configuring a [`Tree`](crate::Tree) at construction time.
+/// See [`Tree::new_with_options`](crate::Tree::new_with_options).
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// # use yrs_tree::{Tree, TreeOptions};
+/// #
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let doc = Arc::new(yrs::Doc::new());
+/// let options = TreeOptions {
+///     undo: true,
+///     max_depth: Some(8),
+///     ..Default::default()
+/// };
+/// let tree = Tree::new_with_options(doc, "test", options)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct TreeOptions {
+    /// Enables [`Tree::undo`](crate::Tree::undo) and [`Tree::redo`](crate::Tree::redo),
+    /// which track changes made to the tree by this client. Defaults to `false`.
+    pub undo: bool,
+    /// Groups the changes made by tree operations within this period of each other
+    /// into a single undo step, until [`Tree::stop_capturing`](crate::Tree::stop_capturing)
+    /// is called. Defaults to `None`, which makes each tree operation its own undo step.
+    /// Only used when `undo` is enabled.
+    pub undo_capture_timeout: Option<Duration>,
+    /// The maximum depth of any node in the tree, where the root node has a depth of 0.
+    /// Creating or moving a node such that it or one of its descendants would be deeper
+    /// fails with [`TreeError::MaxDepthExceeded`](crate::TreeError::MaxDepthExceeded).
+    /// Defaults to no limit.
+    pub max_depth: Option<usize>,
+    /// The maximum number of children of any node. Creating or moving a node into a
+    /// parent that already has this many children fails with
+    /// [`TreeError::MaxChildrenExceeded`](crate::TreeError::MaxChildrenExceeded).
+    /// Defaults to no limit.
+    pub max_children_per_node: Option<usize>,
+    /// Generates the IDs of nodes created with [`NodeApi::create_child`](crate::NodeApi::create_child)
+    /// and [`NodeApi::create_child_at`](crate::NodeApi::create_child_at). The IDs must be
+    /// unique: creating a node fails with [`TreeError::InvalidId`](crate::TreeError::InvalidId)
+    /// if the generated ID is `"<ROOT>"` or belongs to an existing node. Defaults to
+    /// UUID v7 strings.
+    ///
+    /// ```rust
+    /// # use std::sync::{atomic::{AtomicU64, Ordering}, Arc};
+    /// # use yrs_tree::{NodeApi, Tree, TreeOptions};
+    /// #
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let doc = Arc::new(yrs::Doc::new());
+    /// let client_id = doc.client_id();
+    /// let counter = AtomicU64::new(0);
+    ///
+    /// // Short IDs that are unique across clients
+    /// let options = TreeOptions {
+    ///     id_generator: Some(Arc::new(move || {
+    ///         let n = counter.fetch_add(1, Ordering::Relaxed);
+    ///         format!("{:x}-{:x}", client_id, n)
+    ///     })),
+    ///     ..Default::default()
+    /// };
+    /// let tree = Tree::new_with_options(doc, "test", options)?;
+    ///
+    /// let node = tree.create_child()?;
+    /// assert_eq!(node.id().to_string(), format!("{:x}-0", client_id));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub id_generator: Option<Arc<dyn Fn() -> String + Send + Sync>>,
+    /// Records the client ID and time of the last change this client makes to each
+    /// node, whether to its position or its data, in the Yrs document so all clients
+    /// can read it with [`Node::last_modified`](crate::Node::last_modified). Changes
+    /// made by clients that don't enable this aren't recorded. Defaults to `false`.
+    ///
+    /// On `wasm32-unknown-unknown`, the time is read from JavaScript with the `wasm`
+    /// feature enabled. Without it there is no clock, so the time is recorded as the
+    /// Unix epoch.
+    pub track_modifications: bool,
+    /// How siblings with the same position are ordered. Two nodes end up with the
+    /// same position when they are placed at the same index of the same parent
+    /// concurrently, by different clients. Defaults to [`InsertTiebreak::ByNodeId`].
+    ///
+    /// Every client must use the same policy, or they will disagree on the order.
+    pub insert_tiebreak: InsertTiebreak,
+}
+
+/// How a tree orders siblings that have the same position. See
+/// [`TreeOptions::insert_tiebreak`].
+///
+/// Each node's position among its siblings is a fractional index, which is
+/// calculated from its neighbors when the node is created or moved. When two clients
+/// concurrently place a node between the same two neighbors, both nodes get the same
+/// position, and after the clients sync, the policy decides which comes first. Either
+/// way, the nodes stay together between the neighbors they were placed between, and
+/// every client that has seen the same changes agrees on the order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InsertTiebreak {
+    /// Siblings with the same position are ordered by node ID, in ascending order.
+    /// With the default UUID v7 IDs, this puts nodes created at the same position in
+    /// the order they were created, as far as the clocks of the clients that created
+    /// them agree.
+    #[default]
+    ByNodeId,
+    /// Siblings with the same position are ordered by the ID that Yrs gave the
+    /// insertion of each node: first by the client ID of the client that created the
+    /// node, in ascending order, then, for nodes created by the same client, with the
+    /// most recently created node first. Nodes created concurrently by different
+    /// clients are therefore grouped by client, whatever their node IDs, and a node
+    /// created at an index that a node from the same client already has goes before
+    /// it. Moving a node doesn't change its place in this order.
+    Insertion,
+}
+
+/// Options for rendering a tree or subtree as text. See
+/// [`Tree::pretty_print_with_options`](crate::Tree::pretty_print_with_options) and
+/// [`Node::pretty_print_with_options`](crate::Node::pretty_print_with_options).
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// # use yrs_tree::{NodeApi, PrettyPrintOptions, Tree};
+/// #
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let doc = Arc::new(yrs::Doc::new());
+/// # let tree = Tree::new(doc, "test")?;
+/// let a = tree.create_child_with_id("A")?;
+/// a.create_child_with_id("C")?;
+/// tree.create_child_with_id("B")?;
+///
+/// let options = PrettyPrintOptions {
+///     ascii: true,
+///     label: Some(Arc::new(|node| node.id().to_string().to_lowercase())),
+/// };
+/// assert_eq!(
+///     tree.pretty_print_with_options(&options),
+///     "<root>\n|--a\n|  `--c\n`--b\n"
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct PrettyPrintOptions {
+    /// Draws the tree with plain ASCII characters (`|--`, `` `-- ``, and `|`) instead of
+    /// Unicode box-drawing characters. Defaults to `false`.
+    pub ascii: bool,
+    /// Returns the label for each node. Defaults to `None`, which labels each node
+    /// with its ID.
+    pub label: Option<Arc<LabelFn>>,
+}
+
+type LabelFn = dyn Fn(&Arc<Node>) -> String + Send + Sync;
+
+/// Options for writing and reading plain-text outlines, with one node per line,
+/// indented by depth. See [`Tree::to_outline`](crate::Tree::to_outline) and
+/// [`Tree::import_outline`](crate::Tree::import_outline).
+///
+/// ```rust
+/// use yrs_tree::OutlineOptions;
+///
+/// // A Markdown list, with the text of each item stored at "title"
+/// let options = OutlineOptions {
+///     key: "title".to_string(),
+///     bullet: Some("- ".to_string()),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct OutlineOptions {
+    /// The data key that holds the text of each line. Defaults to `"text"`.
+    pub key: String,
+    /// The number of spaces per level of indentation. Defaults to 2.
+    pub indent: usize,
+    /// The number of spaces a tab counts as when reading an outline, so outlines
+    /// indented with tabs, or a mix of tabs and spaces, can be read. Defaults to 2,
+    /// which makes a tab one level with the default `indent`.
+    pub tab_width: usize,
+    /// A marker written at the start of each line's text, after the indentation,
+    /// such as `"- "` for a Markdown list. When reading an outline, the marker is
+    /// removed from the lines that start with it. Defaults to `None`.
+    pub bullet: Option<String>,
+}
+
+impl Default for OutlineOptions {
+    fn default() -> Self {
+        Self {
+            key: "text".to_string(),
+            indent: 2,
+            tab_width: 2,
+            bullet: None,
+        }
+    }
+}
+
+impl fmt::Debug for PrettyPrintOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrettyPrintOptions")
+            .field("ascii", &self.ascii)
+            .field("label", &self.label.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
+impl fmt::Debug for TreeOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TreeOptions")
+            .field("undo", &self.undo)
+            .field("undo_capture_timeout", &self.undo_capture_timeout)
+            .field("max_depth", &self.max_depth)
+            .field("max_children_per_node", &self.max_children_per_node)
+            .field("id_generator", &self.id_generator.as_ref().map(|_| ".."))
+            .field("track_modifications", &self.track_modifications)
+            .field("insert_tiebreak", &self.insert_tiebreak)
+            .finish()
+    }
+}
@@ -0,0 +1,285 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{DeleteStrategy, NodeId, Result, Tree, TreeError};
+
+/// A plain, in-memory copy of a tree's structure, as returned by
+/// [`Tree::to_plain`](crate::Tree::to_plain), for running algorithms that would be
+/// slow or awkward over the Yrs document, such as layout.
+///
+/// Nodes are stored in an arena and referred to by their index in it, which stays
+/// the same for as long as the plain tree exists. The root node is always at index
+/// [`PlainTree::ROOT`]. Methods that take an index panic if there was never a node at
+/// it, like slice indexing. Results can be mapped back to the tree with
+/// [`PlainTree::id`] and [`PlainTree::index_of`], and structural changes made to the
+/// plain tree can be written back with [`PlainTree::apply_to`].
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use yrs_tree::{NodeApi, PlainTree, Tree};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let tree = Tree::new(Arc::new(yrs::Doc::new()), "test")?;
+/// let a = tree.create_child_with_id("A")?;
+/// a.create_child_with_id("C")?;
+/// tree.create_child_with_id("B")?;
+///
+/// let mut plain = tree.to_plain();
+/// let b = plain.index_of(&"B".into()).unwrap();
+/// plain.insert(b, None, "D")?;
+/// plain.move_to(b, PlainTree::ROOT, Some(0))?;
+///
+/// plain.apply_to(&tree)?;
+/// assert_eq!(tree.pretty_print(), "<ROOT>\n├──B\n│  └──D\n└──A\n   └──C\n");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PlainTree {
+    nodes: Vec<PlainNode>,
+    indices: HashMap<NodeId, usize>,
+}
+
+#[derive(Debug, Clone)]
+struct PlainNode {
+    id: NodeId,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    removed: bool,
+}
+
+impl PlainTree {
+    /// The index of the root node.
+    pub const ROOT: usize = 0;
+
+    /// Creates a plain tree with only the root node.
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![PlainNode {
+                id: NodeId::Root,
+                parent: None,
+                children: vec![],
+                removed: false,
+            }],
+            indices: HashMap::from([(NodeId::Root, Self::ROOT)]),
+        }
+    }
+
+    /// Returns the number of nodes in the tree, not counting the root node.
+    pub fn len(&self) -> usize {
+        self.indices.len() - 1
+    }
+
+    /// Returns `true` if the tree has no nodes other than the root node.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the ID of the node at the given index.
+    ///
+    /// Panics if there is no node at the index.
+    pub fn id(&self, index: usize) -> &NodeId {
+        &self.nodes[index].id
+    }
+
+    /// Returns the index of the node with the given ID, or `None` if the tree doesn't
+    /// contain it.
+    pub fn index_of(&self, id: &NodeId) -> Option<usize> {
+        self.indices.get(id).copied()
+    }
+
+    /// Returns `true` if the tree contains the node at the given index, which is
+    /// the case unless it was removed.
+    pub fn contains(&self, index: usize) -> bool {
+        self.nodes.get(index).is_some_and(|node| !node.removed)
+    }
+
+    /// Returns the index of the node's parent, or `None` for the root node or a node
+    /// that was removed.
+    pub fn parent(&self, index: usize) -> Option<usize> {
+        self.nodes.get(index).and_then(|node| node.parent)
+    }
+
+    /// Returns the indices of the node's children, in order.
+    pub fn children(&self, index: usize) -> &[usize] {
+        self.nodes
+            .get(index)
+            .map(|node| node.children.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns the indices of the nodes in the tree in depth-first order, starting
+    /// with the root node.
+    pub fn depth_first(&self) -> Vec<usize> {
+        let mut order = vec![];
+        let mut stack = vec![Self::ROOT];
+        while let Some(index) = stack.pop() {
+            order.push(index);
+            stack.extend(self.nodes[index].children.iter().rev());
+        }
+
+        order
+    }
+
+    /// Adds a node with the given ID as a child of `parent` at the given index,
+    /// returning the new node's index. Passing `None` as the index places it after
+    /// the parent's existing children.
+    ///
+    /// Returns [`TreeError::InvalidId`] if the ID is `<ROOT>` or is already in the
+    /// tree, and [`TreeError::MissingParent`] if the parent was removed.
+    pub fn insert(
+        &mut self,
+        parent: usize,
+        index: Option<usize>,
+        id: impl Into<NodeId>,
+    ) -> Result<usize> {
+        let id = id.into();
+        if id == NodeId::Root || self.indices.contains_key(&id) {
+            return Err(TreeError::InvalidId(id.to_string()));
+        }
+        self.check_parent(parent)?;
+
+        let node = self.nodes.len();
+        self.nodes.push(PlainNode {
+            id: id.clone(),
+            parent: None,
+            children: vec![],
+            removed: false,
+        });
+        self.indices.insert(id, node);
+        self.place(node, parent, index);
+
+        Ok(node)
+    }
+
+    /// Moves the node to `parent`, so that it ends up at the given index among the
+    /// parent's children. Passing `None` as the index places it after the parent's
+    /// other children.
+    ///
+    /// Returns [`TreeError::UnsupportedOperation`] for the root node,
+    /// [`TreeError::InvalidTarget`] if the node was removed,
+    /// [`TreeError::MissingParent`] if the parent was removed, and
+    /// [`TreeError::Cycle`] if the parent is the node itself or one of its
+    /// descendants.
+    pub fn move_to(&mut self, node: usize, parent: usize, index: Option<usize>) -> Result<()> {
+        if node == Self::ROOT {
+            return Err(TreeError::UnsupportedOperation(
+                "Cannot move the root node".to_string(),
+            ));
+        }
+        if !self.contains(node) {
+            return Err(TreeError::InvalidTarget(self.nodes[node].id.clone()));
+        }
+        self.check_parent(parent)?;
+
+        let mut ancestor = Some(parent);
+        while let Some(current) = ancestor {
+            if current == node {
+                return Err(TreeError::Cycle(
+                    self.nodes[node].id.clone(),
+                    self.nodes[parent].id.clone(),
+                ));
+            }
+            ancestor = self.nodes[current].parent;
+        }
+
+        self.unlink(node);
+        self.place(node, parent, index);
+
+        Ok(())
+    }
+
+    /// Removes the node and its descendants from the tree. The indices of the other
+    /// nodes don't change.
+    ///
+    /// Returns [`TreeError::InvalidTarget`] for the root node or a node that was
+    /// already removed.
+    pub fn remove(&mut self, node: usize) -> Result<()> {
+        if node == Self::ROOT || !self.contains(node) {
+            return Err(TreeError::InvalidTarget(self.nodes[node].id.clone()));
+        }
+
+        self.unlink(node);
+        let mut stack = vec![node];
+        while let Some(index) = stack.pop() {
+            let removed = &mut self.nodes[index];
+            removed.removed = true;
+            removed.parent = None;
+            stack.append(&mut removed.children);
+            self.indices.remove(&removed.id);
+        }
+
+        Ok(())
+    }
+
+    /// Changes `tree` to have the same structure as this plain tree, in a single
+    /// transaction, as with [`Tree::transact`]. Nodes are moved, and created or
+    /// deleted along with their data, as needed; nodes that are already in the right
+    /// place are left alone. Nodes that are created have no data.
+    ///
+    /// The tree doesn't have to be the one this plain tree was made from. If a step
+    /// fails, for example because it would exceed one of the tree's limits, the error
+    /// is returned and the steps before it are still applied.
+    pub fn apply_to(&self, tree: &Arc<Tree>) -> Result<()> {
+        tree.transact(|txn| {
+            // Each parent is in place before its children are, so moving a child
+            // under it can't create a cycle
+            for parent in self.depth_first() {
+                let parent_id = &self.nodes[parent].id;
+                for (i, &child) in self.nodes[parent].children.iter().enumerate() {
+                    let id = &self.nodes[child].id;
+                    if !txn.has_node(id) {
+                        txn.create_child_with_id_at(parent_id, id.clone(), Some(i))?;
+                    } else if txn.children(parent_id).get(i) != Some(id) {
+                        txn.move_nodes(std::slice::from_ref(id), parent_id, Some(i))?;
+                    }
+                }
+            }
+
+            // Every node that is kept has been moved under a kept parent, so the
+            // nodes left over are whole subtrees
+            let deleted = txn
+                .structure
+                .nodes
+                .keys()
+                .filter(|id| {
+                    !self.indices.contains_key(*id)
+                        && txn
+                            .parent(id)
+                            .is_some_and(|parent| self.indices.contains_key(&parent))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            for id in deleted {
+                txn.delete(&id, DeleteStrategy::Cascade)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    fn check_parent(&self, parent: usize) -> Result<()> {
+        if self.contains(parent) {
+            Ok(())
+        } else {
+            Err(TreeError::MissingParent(self.nodes[parent].id.clone()))
+        }
+    }
+
+    fn unlink(&mut self, node: usize) {
+        if let Some(parent) = self.nodes[node].parent.take() {
+            self.nodes[parent].children.retain(|&child| child != node);
+        }
+    }
+
+    fn place(&mut self, node: usize, parent: usize, index: Option<usize>) {
+        let children = &mut self.nodes[parent].children;
+        let index = index.unwrap_or(children.len()).min(children.len());
+        children.insert(index, node);
+        self.nodes[node].parent = Some(parent);
+    }
+}
+
+impl Default for PlainTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,287 @@
+use std::sync::Arc;
+
+use crate::{
+    events::{ChangeScope, DataEvent, NodeEvent, Subscription},
+    iter::{TraversalOrder, TreeIter},
+    IntegrityError, Modification, Node, NodeApi, NodeId, Result, SubtreeExport, Tree, TreeSnapshot,
+    TreeStats,
+};
+
+/// A read-only handle to a [`Tree`], returned by [`Tree::readonly`]. It can query
+/// the tree and subscribe to its changes, but has no methods that modify it, and the
+/// nodes it returns are [`ReadonlyNode`]s. It reflects changes made through the
+/// tree, or applied to its Yrs document, as they happen.
+///
+/// Subscriptions made through the view receive [`NodeEvent`]s and [`DataEvent`]s,
+/// which refer to nodes by ID; tree events aren't available, since they carry the
+/// tree itself.
+///
+/// ```compile_fail
+/// # use std::sync::Arc;
+/// # use yrs_tree::Tree;
+/// # let doc = Arc::new(yrs::Doc::new());
+/// # let tree = Tree::new(doc, "test").unwrap();
+/// let view = tree.readonly();
+/// view.root().create_child(); // no such method
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReadonlyTree {
+    tree: Arc<Tree>,
+}
+
+impl ReadonlyTree {
+    pub(crate) fn new(tree: Arc<Tree>) -> Self {
+        Self { tree }
+    }
+
+    /// Returns the ID of the root node.
+    pub fn id(&self) -> &NodeId {
+        self.tree.id()
+    }
+
+    /// Returns the root node of the tree.
+    pub fn root(&self) -> ReadonlyNode {
+        ReadonlyNode::new(self.tree.root())
+    }
+
+    /// Returns true if the tree has a node with the given ID.
+    pub fn has_node(&self, id: impl Into<NodeId>) -> bool {
+        self.tree.has_node(id)
+    }
+
+    /// Returns the node with the given ID. See [`Tree::get_node`].
+    pub fn get_node(&self, id: impl Into<NodeId>) -> Option<ReadonlyNode> {
+        self.tree.get_node(id).map(ReadonlyNode::new)
+    }
+
+    /// Returns the children of the root node.
+    pub fn children(&self) -> Vec<ReadonlyNode> {
+        self.root().children()
+    }
+
+    /// Returns the IDs of the children of the root node.
+    pub fn children_ids(&self) -> Vec<NodeId> {
+        self.tree.children_ids()
+    }
+
+    /// Returns an iterator over the tree's nodes, starting with the root node, in
+    /// the given order. See [`NodeApi::traverse`].
+    pub fn traverse(&self, order: TraversalOrder) -> impl Iterator<Item = ReadonlyNode> {
+        self.tree.traverse(order).map(ReadonlyNode::new)
+    }
+
+    /// Returns all nodes in the tree, excluding the root node, for which the predicate
+    /// returns `true`, in depth-first order.
+    pub fn nodes_where<F>(&self, mut predicate: F) -> Vec<ReadonlyNode>
+    where
+        F: FnMut(&ReadonlyNode) -> bool,
+    {
+        self.traverse(TraversalOrder::DepthFirst)
+            .skip(1)
+            .filter(|node| predicate(node))
+            .collect()
+    }
+
+    /// See [`Tree::is_poisoned`].
+    pub fn is_poisoned(&self) -> bool {
+        self.tree.is_poisoned()
+    }
+
+    /// See [`Tree::check_integrity`].
+    pub fn check_integrity(&self) -> std::result::Result<(), Vec<IntegrityError>> {
+        self.tree.check_integrity()
+    }
+
+    /// See [`Tree::stats`].
+    pub fn stats(&self) -> TreeStats {
+        self.tree.stats()
+    }
+
+    /// See [`Tree::snapshot`].
+    pub fn snapshot(&self) -> TreeSnapshot {
+        self.tree.snapshot()
+    }
+
+    /// See [`Tree::pretty_print`].
+    pub fn pretty_print(&self) -> String {
+        self.tree.pretty_print()
+    }
+
+    /// See [`Tree::pretty_print_with_key`].
+    pub fn pretty_print_with_key(&self, key: &str) -> String {
+        self.tree.pretty_print_with_key(key)
+    }
+
+    /// See [`Tree::to_dot`].
+    pub fn to_dot(&self) -> String {
+        self.tree.to_dot()
+    }
+
+    /// See [`Tree::to_mermaid`].
+    pub fn to_mermaid(&self) -> String {
+        self.tree.to_mermaid()
+    }
+
+    /// See [`Tree::to_mermaid_with`].
+    pub fn to_mermaid_with(&self, label: impl Fn(&Arc<Node>) -> String) -> String {
+        self.tree.to_mermaid_with(label)
+    }
+
+    /// See [`Tree::on_node_events`].
+    pub fn on_node_events(
+        &self,
+        callback: impl Fn(&[NodeEvent]) + Send + Sync + 'static,
+    ) -> Subscription {
+        self.tree.on_node_events(callback)
+    }
+
+    /// See [`Tree::observe_data_deep`].
+    pub fn observe_data_deep(
+        &self,
+        callback: impl Fn(&DataEvent) + Send + Sync + 'static,
+    ) -> Subscription {
+        self.tree.observe_data_deep(callback)
+    }
+}
+
+/// Iterates over all of the nodes in the tree in depth-first order, starting with the
+/// root node; see [`Tree::iter`].
+impl IntoIterator for &ReadonlyTree {
+    type Item = ReadonlyNode;
+    type IntoIter = std::iter::Map<TreeIter, fn(Arc<Node>) -> ReadonlyNode>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tree.iter().map(ReadonlyNode::new)
+    }
+}
+
+/// A read-only handle to a node, returned by a [`ReadonlyTree`]. It has the query
+/// methods of [`NodeApi`] and [`Node`], but none of the methods that modify the tree.
+#[derive(Clone, Debug)]
+pub struct ReadonlyNode {
+    node: Arc<Node>,
+}
+
+impl ReadonlyNode {
+    fn new(node: Arc<Node>) -> Self {
+        Self { node }
+    }
+
+    fn wrap(nodes: Vec<Arc<Node>>) -> Vec<Self> {
+        nodes.into_iter().map(Self::new).collect()
+    }
+
+    /// Returns the ID of the node.
+    pub fn id(&self) -> &NodeId {
+        self.node.id()
+    }
+
+    /// Returns the parent of the node.
+    pub fn parent(&self) -> Option<ReadonlyNode> {
+        self.node.parent().map(Self::new)
+    }
+
+    /// Returns the ancestors of the node, starting with the node's parent and ending
+    /// at the root node.
+    pub fn ancestors(&self) -> Vec<ReadonlyNode> {
+        Self::wrap(self.node.ancestors())
+    }
+
+    /// Returns the IDs of the ancestors of the node, in the same order as
+    /// [`ReadonlyNode::ancestors`].
+    pub fn ancestor_ids(&self) -> Vec<NodeId> {
+        self.node.ancestor_ids()
+    }
+
+    /// Returns the children of the node.
+    pub fn children(&self) -> Vec<ReadonlyNode> {
+        Self::wrap(self.node.children())
+    }
+
+    /// Returns the IDs of the children of the node.
+    pub fn children_ids(&self) -> Vec<NodeId> {
+        self.node.children_ids()
+    }
+
+    /// Returns the child at the given position, or `None` if `index` is out of bounds.
+    pub fn get_child_at_index(&self, index: usize) -> Option<ReadonlyNode> {
+        self.node.get_child_at_index(index).map(Self::new)
+    }
+
+    /// Returns the number of children the node has.
+    pub fn get_child_count(&self) -> usize {
+        self.node.get_child_count()
+    }
+
+    /// Returns the descendants of the node, in the given order.
+    pub fn descendants(&self, order: TraversalOrder) -> Vec<ReadonlyNode> {
+        Self::wrap(self.node.descendants(order))
+    }
+
+    /// Returns the children of the node's parent, including the node itself.
+    pub fn siblings(&self) -> Vec<ReadonlyNode> {
+        Self::wrap(self.node.siblings())
+    }
+
+    /// Returns the position of the node among its parent's children.
+    pub fn index_in_parent(&self) -> Option<usize> {
+        self.node.index_in_parent()
+    }
+
+    /// Returns the depth of the node; the root node is at depth 0.
+    pub fn depth(&self) -> usize {
+        self.node.depth()
+    }
+
+    /// Returns an iterator over the node and its descendants, in the given order.
+    pub fn traverse(&self, order: TraversalOrder) -> impl Iterator<Item = ReadonlyNode> {
+        self.node.traverse(order).map(Self::new)
+    }
+
+    /// See [`Node::get`].
+    pub fn get(&self, key: &str) -> Result<Option<yrs::Out>> {
+        self.node.get(key)
+    }
+
+    /// See [`Node::get_as`].
+    pub fn get_as<V: serde::de::DeserializeOwned>(&self, key: &str) -> Result<V> {
+        self.node.get_as(key)
+    }
+
+    /// See [`Node::get_json`].
+    pub fn get_json<T: serde::de::DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        self.node.get_json(key)
+    }
+
+    /// See [`Node::last_modified`].
+    pub fn last_modified(&self) -> Option<Modification> {
+        self.node.last_modified()
+    }
+
+    /// See [`Node::pretty_print`].
+    pub fn pretty_print(&self) -> String {
+        self.node.pretty_print()
+    }
+
+    /// See [`Node::export_subtree`].
+    pub fn export_subtree(&self) -> SubtreeExport {
+        self.node.export_subtree()
+    }
+
+    /// See [`Node::on_change`].
+    pub fn on_change(
+        &self,
+        scope: ChangeScope,
+        callback: impl Fn(&NodeEvent) + Send + Sync + 'static,
+    ) -> Subscription {
+        self.node.on_change(scope, callback)
+    }
+
+    /// See [`Node::observe_data`].
+    pub fn observe_data(
+        &self,
+        callback: impl Fn(&DataEvent) + Send + Sync + 'static,
+    ) -> Subscription {
+        self.node.observe_data(callback)
+    }
+}
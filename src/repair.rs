@@ -0,0 +1,43 @@
+use crate::NodeId;
+
+/// What was changed by [`Tree::repair`](crate::Tree::repair).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RepairReport {
+    /// Every change made to the document, in the order it was made.
+    pub actions: Vec<RepairAction>,
+}
+
+impl RepairReport {
+    /// Returns `true` if the document didn't need repairing.
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+}
+
+/// A change made to the Yrs document by [`Tree::repair`](crate::Tree::repair).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepairAction {
+    /// An entry in the tree's map that isn't a node container was removed.
+    RemovedEntry { key: String },
+    /// An entry in the node's parent history was removed because the parent doesn't
+    /// exist, or the entry isn't a number.
+    RemovedEdge { node: NodeId, parent: NodeId },
+    /// The node was listed under more than one parent; it was kept under `kept`.
+    DeduplicatedParent {
+        node: NodeId,
+        kept: NodeId,
+        dropped: Vec<NodeId>,
+    },
+    /// The lost and found node given to [`Tree::repair_into`](crate::Tree::repair_into)
+    /// was created under the root node.
+    CreatedLostAndFound { node: NodeId },
+    /// The node had no parent that exists, so it was moved to the root node or the
+    /// lost and found node.
+    ReattachedOrphan { node: NodeId, parent: NodeId },
+    /// The node was part of a cycle, so it was moved to `new_parent`, the most recent
+    /// of its previous parents outside of the cycle, or the root node.
+    CycleBroken { node: NodeId, new_parent: NodeId },
+    /// The node's fractional index couldn't be read, so it was moved to the end of its
+    /// parent's children.
+    Reindexed { node: NodeId },
+}
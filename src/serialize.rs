@@ -0,0 +1,142 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use serde::{de::Error as _, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Node, NodeApi, NodeId, Tree};
+
+impl Serialize for NodeId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_ref())
+    }
+}
+
+impl<'de> Deserialize<'de> for NodeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(NodeId::from)
+    }
+}
+
+/// Serializes the node's ID and data, without its children:
+/// `{"id": "...", "data": {...}}`.
+impl Serialize for Node {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("id", &self.id)?;
+        map.serialize_entry("data", &node_data(&self.tree, &self.id)?)?;
+        map.end()
+    }
+}
+
+/// Serializes the whole tree as nested nodes, starting at the root node:
+/// `{"id": "<ROOT>", "data": {}, "children": [{"id": "...", "data": {...}, "children": [...]}]}`.
+///
+/// Use [`Tree::from_serialized`] to build a new tree from the serialized data.
+impl Serialize for Tree {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializeSubtree {
+            tree: self,
+            id: NodeId::Root,
+        }
+        .serialize(serializer)
+    }
+}
+
+struct SerializeSubtree<'a> {
+    tree: &'a Tree,
+    id: NodeId,
+}
+
+impl Serialize for SerializeSubtree<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let children = self
+            .tree
+            .get_children(&self.id)
+            .into_iter()
+            .map(|id| SerializeSubtree {
+                tree: self.tree,
+                id,
+            })
+            .collect::<Vec<_>>();
+
+        let mut map = serializer.serialize_map(Some(3))?;
+        map.serialize_entry("id", &self.id)?;
+        map.serialize_entry("data", &node_data(self.tree, &self.id)?)?;
+        map.serialize_entry("children", &children)?;
+        map.end()
+    }
+}
+
+fn node_data<E: serde::ser::Error>(
+    tree: &Tree,
+    id: &NodeId,
+) -> Result<BTreeMap<String, yrs::Any>, E> {
+    // Sort the keys so the output is stable
+    tree.read_all_data(id)
+        .map(|data| data.into_iter().collect())
+        .map_err(E::custom)
+}
+
+#[derive(Deserialize)]
+struct SerializedNode {
+    id: NodeId,
+    #[serde(default)]
+    data: HashMap<String, yrs::Any>,
+    #[serde(default)]
+    children: Vec<SerializedNode>,
+}
+
+impl Tree {
+    /// Builds a new tree in a fresh Yrs document, from data in the format produced by
+    /// the tree's `Serialize` implementation. The tree is stored in the document under
+    /// the given container name. Requires the `serde` feature.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use yrs_tree::{NodeApi, Tree};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let doc = Arc::new(yrs::Doc::new());
+    /// let tree = Tree::new(doc, "test")?;
+    /// tree.create_child_with_id("1")?.set("name", "Folder")?;
+    ///
+    /// let json = serde_json::to_string(&tree)?;
+    /// let copy = Tree::from_serialized(&mut serde_json::Deserializer::from_str(&json), "test")?;
+    ///
+    /// let node = copy.get_node("1").unwrap();
+    /// assert_eq!(node.get_as::<String>("name")?, "Folder");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_serialized<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        tree_name: &str,
+    ) -> Result<Arc<Self>, D::Error> {
+        let root = SerializedNode::deserialize(deserializer)?;
+        if root.id != NodeId::Root {
+            return Err(D::Error::custom(format!(
+                "expected the root node, found {}",
+                root.id
+            )));
+        }
+
+        let doc = Arc::new(yrs::Doc::new());
+        let tree = Tree::new(doc, tree_name).map_err(D::Error::custom)?;
+
+        let mut stack = vec![(tree.root(), root.children)];
+        while let Some((parent, children)) = stack.pop() {
+            for child in children {
+                let node = parent
+                    .create_child_with_id(child.id)
+                    .map_err(D::Error::custom)?;
+                for (key, value) in child.data {
+                    node.set(&key, value).map_err(D::Error::custom)?;
+                }
+                stack.push((node, child.children));
+            }
+        }
+
+        Ok(tree)
+    }
+}
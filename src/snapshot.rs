@@ -0,0 +1,124 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{NodeId, Result, TreeError};
+
+/// The logical state of a tree at one point in time, as returned by
+/// [`Tree::snapshot`](crate::Tree::snapshot): every node's parent, its position
+/// among its siblings, and its data. Use [`Tree::restore`](crate::Tree::restore)
+/// to return a tree to this state.
+///
+/// With the `serde` feature enabled, snapshots can be serialized, so they can be
+/// stored outside of the Yrs document.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TreeSnapshot {
+    /// Every node except the root, in depth-first order, so parents come before
+    /// their children and siblings are in order.
+    pub(crate) nodes: Vec<SnapshotNode>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct SnapshotNode {
+    pub(crate) id: NodeId,
+    pub(crate) parent: NodeId,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) data: BTreeMap<String, yrs::Any>,
+}
+
+impl TreeSnapshot {
+    /// Returns the number of nodes in the snapshot, not counting the root node.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if the snapshot has no nodes other than the root node.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns `true` if the snapshot contains the node.
+    pub fn contains(&self, id: &NodeId) -> bool {
+        *id == NodeId::Root || self.nodes.iter().any(|node| node.id == *id)
+    }
+}
+
+/// A node and its descendants, with their data, as returned by
+/// [`Node::export_subtree`](crate::Node::export_subtree). The export doesn't refer to
+/// the document it came from, so it can be imported into any tree with
+/// [`Node::import_subtree`](crate::Node::import_subtree).
+///
+/// With the `serde` feature enabled, exports can be serialized, for example to put
+/// them on the clipboard.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubtreeExport {
+    /// The exported node followed by its descendants, in depth-first order.
+    pub(crate) nodes: Vec<SnapshotNode>,
+}
+
+impl SubtreeExport {
+    /// Returns the ID the exported node had in the tree it was exported from, or
+    /// `None` if the export is empty, which only happens if it was deserialized from
+    /// malformed input.
+    pub fn id(&self) -> Option<&NodeId> {
+        self.nodes.first().map(|node| &node.id)
+    }
+
+    /// Checks that the export describes a single subtree: it isn't empty, the first
+    /// node is the exported node, every other node's parent comes before it, and no
+    /// ID appears twice. Exports can be deserialized, so this is checked before one
+    /// is imported.
+    pub(crate) fn validate(&self) -> Result<()> {
+        let Some(first) = self.nodes.first() else {
+            return Err(TreeError::InvalidExport(
+                "the export has no nodes".to_string(),
+            ));
+        };
+
+        let mut seen = HashSet::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            if i > 0 && !seen.contains(&node.parent) {
+                return Err(TreeError::InvalidExport(format!(
+                    "parent {} of node {} does not come before it",
+                    node.parent, node.id
+                )));
+            }
+            if !seen.insert(&node.id) {
+                return Err(TreeError::InvalidExport(format!(
+                    "node {} appears more than once",
+                    node.id
+                )));
+            }
+        }
+
+        if seen.contains(&first.parent) {
+            return Err(TreeError::InvalidExport(format!(
+                "the exported node {} is a descendant of itself",
+                first.id
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// A node as a row of a flat table, as returned by [`Tree::to_rows`](crate::Tree::to_rows)
+/// and accepted by [`Tree::from_rows`](crate::Tree::from_rows), for storing trees in
+/// places like SQL tables that record each node's parent.
+///
+/// With the `serde` feature enabled, rows can be serialized.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeRow {
+    /// The ID of the node.
+    pub id: NodeId,
+    /// The ID of the node's parent, which is [`NodeId::Root`] for the root node's
+    /// children.
+    pub parent: NodeId,
+    /// The position of the node among its parent's children.
+    pub index: usize,
+    /// The node's data.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub data: BTreeMap<String, yrs::Any>,
+}
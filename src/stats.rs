@@ -0,0 +1,21 @@
+/// Measurements of a tree's shape, as returned by [`Tree::stats`](crate::Tree::stats).
+///
+/// The root node is not counted as a node, and is at depth 0, so a tree with only
+/// the root node has all fields set to zero.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TreeStats {
+    /// The number of nodes in the tree.
+    pub node_count: usize,
+    /// The depth of the deepest node; children of the root node are at depth 1.
+    pub max_depth: usize,
+    /// The number of nodes without children.
+    pub leaf_count: usize,
+    /// The average number of children of the nodes that have any, including the root
+    /// node: `node_count` divided by the number of nodes with children.
+    pub average_branching_factor: f64,
+    /// The depth with the most nodes. If several depths have the same number of
+    /// nodes, this is the shallowest of them.
+    pub widest_depth: usize,
+    /// The number of nodes at `widest_depth`.
+    pub width_at_widest: usize,
+}
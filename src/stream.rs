@@ -0,0 +1,81 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    pin::Pin,
+    sync::{Arc, Weak},
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+use tokio::sync::mpsc;
+
+use crate::{Tree, TreeEvent};
+
+/// A stream of the events emitted by a tree, created with [`Tree::events`].
+///
+/// Each item holds the events caused by a single transaction on the Yrs document.
+/// Dropping the stream stops the tree from sending events to it.
+///
+/// Since tree events hold a reference to the tree, which can't be shared across
+/// threads, the stream must be polled on the thread that owns the tree, for example
+/// from a current-thread runtime or a `LocalSet`.
+pub struct TreeEventStream {
+    id: usize,
+    tree: Weak<Tree>,
+    receiver: mpsc::UnboundedReceiver<Vec<TreeEvent>>,
+}
+
+impl Stream for TreeEventStream {
+    type Item = Vec<TreeEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for TreeEventStream {
+    fn drop(&mut self) {
+        if let Some(tree) = self.tree.upgrade() {
+            tree.event_streams.remove(self.id);
+        }
+    }
+}
+
+/// The senders for the event streams created from a tree.
+#[derive(Default)]
+pub(crate) struct EventStreams {
+    next_id: Cell<usize>,
+    senders: RefCell<HashMap<usize, mpsc::UnboundedSender<Vec<TreeEvent>>>>,
+}
+
+impl EventStreams {
+    pub fn create(&self, tree: &Arc<Tree>) -> TreeEventStream {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.senders.borrow_mut().insert(id, sender);
+
+        TreeEventStream {
+            id,
+            tree: Arc::downgrade(tree),
+            receiver,
+        }
+    }
+
+    pub fn send(&self, events: &[TreeEvent]) {
+        for sender in self.senders.borrow().values() {
+            // A send only fails once the stream is dropped, which unregisters it
+            let _ = sender.send(events.to_vec());
+        }
+    }
+
+    fn remove(&self, id: usize) {
+        self.senders.borrow_mut().remove(&id);
+    }
+
+    #[cfg(test)]
+    pub fn stream_count(&self) -> usize {
+        self.senders.borrow().len()
+    }
+}
@@ -0,0 +1,206 @@
+use std::{collections::HashMap, sync::Arc};
+
+use yrs::{block::Prelim, MapRef, TransactionMut};
+
+use crate::{
+    snapshot::SnapshotNode, tree_structure::TreeStructure, DeleteStrategy, NodeId, Result,
+    SubtreeExport, Tree, TreeError,
+};
+
+/// A set of tree operations that are applied in a single Yrs transaction.
+/// See [`Tree::transact`].
+///
+/// Nodes are referred to by ID, since [`Node`](crate::Node) handles perform their
+/// operations in transactions of their own, which can't be started while this one
+/// is open. The methods that read the tree reflect the changes made so far in the
+/// transaction.
+pub struct TreeTxn<'a> {
+    pub(crate) tree: &'a Arc<Tree>,
+    pub(crate) txn: TransactionMut<'a>,
+    pub(crate) map: MapRef,
+    pub(crate) structure: TreeStructure,
+    /// Subtrees removed by cascade deletes, for [`Tree::on_node_deleted`].
+    pub(crate) deleted: Vec<SubtreeExport>,
+}
+
+impl TreeTxn<'_> {
+    /// Creates a new child of `parent` with a generated ID, returning the new node's ID.
+    pub fn create_child(&mut self, parent: &NodeId) -> Result<NodeId> {
+        let id = self.tree.generate_child_id(|id| self.has_node(id))?;
+        self.create_child_with_id_at(parent, id, None)
+    }
+
+    /// Creates a new child of `parent` with a generated ID at the given index,
+    /// returning the new node's ID.
+    pub fn create_child_at(&mut self, parent: &NodeId, index: usize) -> Result<NodeId> {
+        let id = self.tree.generate_child_id(|id| self.has_node(id))?;
+        self.create_child_with_id_at(parent, id, Some(index))
+    }
+
+    /// Creates a new child of `parent` with the given ID, returning the ID.
+    pub fn create_child_with_id(
+        &mut self,
+        parent: &NodeId,
+        id: impl Into<NodeId>,
+    ) -> Result<NodeId> {
+        self.create_child_with_id_at(parent, id, None)
+    }
+
+    /// Creates a new child of `parent` with the given ID at the given index, returning
+    /// the ID. Passing `None` as the index creates the node after the parent's
+    /// existing children.
+    pub fn create_child_with_id_at(
+        &mut self,
+        parent: &NodeId,
+        id: impl Into<NodeId>,
+        index: Option<usize>,
+    ) -> Result<NodeId> {
+        let id = id.into();
+
+        if id == NodeId::Root {
+            return Err(TreeError::InvalidId(
+                "<ROOT> cannot be used as a node ID".to_string(),
+            ));
+        }
+
+        self.update_node(&id, parent, index)?;
+        Ok(id)
+    }
+
+    /// Moves the node to `parent` at the given index; see [`NodeApi::move_to`](crate::NodeApi::move_to).
+    pub fn move_to(&mut self, id: &NodeId, parent: &NodeId, index: Option<usize>) -> Result<()> {
+        if *id == NodeId::Root {
+            return Err(TreeError::UnsupportedOperation(
+                "Cannot move the root node".to_string(),
+            ));
+        }
+
+        if !self.has_node(id) {
+            return Err(TreeError::InvalidTarget(id.clone()));
+        }
+        self.structure.check_index(parent, index)?;
+
+        self.update_node(id, parent, index)
+    }
+
+    /// Deletes the node; see [`NodeApi::delete`](crate::NodeApi::delete).
+    pub fn delete(&mut self, id: &NodeId, strategy: DeleteStrategy) -> Result<()> {
+        let plan = self.tree.plan_delete(&self.structure, id, strategy)?;
+
+        if strategy == DeleteStrategy::Cascade && self.tree.observer.has_delete_listeners() {
+            let export = self.capture_subtree(id)?;
+            self.deleted.push(export);
+        }
+
+        if let Some((parent, children)) = &plan.promoted {
+            for child in children {
+                self.update_node(child, parent, None)?;
+            }
+        }
+
+        self.structure
+            .delete_nodes(&plan.to_delete, &self.map, &mut self.txn)?;
+        for id in plan.to_delete.iter() {
+            self.structure.remove_node(id);
+        }
+
+        Ok(())
+    }
+
+    /// Sets a value on the node at the given key; see [`Node::set`](crate::Node::set).
+    pub fn set<V: Prelim + Into<yrs::Any>>(
+        &mut self,
+        id: &NodeId,
+        key: &str,
+        value: V,
+    ) -> Result<V::Return> {
+        self.check_target(id)?;
+        self.structure
+            .set_data(id, key, value, &self.map, &mut self.txn)
+    }
+
+    /// Returns `true` if the node exists.
+    pub fn has_node(&self, id: &NodeId) -> bool {
+        self.structure.nodes.contains_key(id)
+    }
+
+    /// Returns the ID of the node's parent, or `None` for the root node or a node
+    /// that doesn't exist.
+    pub fn parent(&self, id: &NodeId) -> Option<NodeId> {
+        self.structure.get_parent(id).cloned()
+    }
+
+    /// Returns the IDs of the node's children, in order.
+    pub fn children(&self, id: &NodeId) -> Vec<NodeId> {
+        self.structure.get_children(id).unwrap_or_default().to_vec()
+    }
+
+    /// Moves existing nodes next to each other under `parent`, computing fractional
+    /// indices between their new neighbors; see [`Tree::move_many`].
+    pub(crate) fn move_nodes(
+        &mut self,
+        ids: &[NodeId],
+        parent: &NodeId,
+        index: Option<usize>,
+    ) -> Result<()> {
+        self.tree.check_limits(&self.structure, ids, parent)?;
+        self.structure
+            .move_nodes(ids, parent, index, &self.map, &mut self.txn)?;
+        for id in ids {
+            self.structure.refresh_node(id, &self.map, &self.txn)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn get_all_data(&self, id: &NodeId) -> Result<HashMap<String, yrs::Any>> {
+        if *id == NodeId::Root {
+            return Ok(HashMap::new());
+        }
+
+        self.structure.get_all_data(id, &self.map, &self.txn)
+    }
+
+    /// Captures the node and its descendants in depth-first order, as they are in
+    /// the transaction so far.
+    fn capture_subtree(&self, id: &NodeId) -> Result<SubtreeExport> {
+        let mut nodes = vec![];
+        let mut stack = vec![id.clone()];
+        while let Some(id) = stack.pop() {
+            stack.extend(self.children(&id).into_iter().rev());
+            nodes.push(SnapshotNode {
+                parent: self.parent(&id).unwrap_or_default(),
+                data: self.get_all_data(&id)?.into_iter().collect(),
+                id,
+            });
+        }
+
+        Ok(SubtreeExport { nodes })
+    }
+
+    pub(crate) fn remove_data(&mut self, id: &NodeId, key: &str) -> Result<()> {
+        self.check_target(id)?;
+        self.structure
+            .remove_data(id, key, &self.map, &mut self.txn)
+    }
+
+    /// Returns [`TreeError::InvalidTarget`] for the root node, which has no data, and
+    /// for nodes that aren't in the tree, including ones deleted earlier in the
+    /// transaction.
+    fn check_target(&self, id: &NodeId) -> Result<()> {
+        if *id == NodeId::Root || !self.has_node(id) {
+            return Err(TreeError::InvalidTarget(id.clone()));
+        }
+
+        Ok(())
+    }
+
+    fn update_node(&mut self, id: &NodeId, parent: &NodeId, index: Option<usize>) -> Result<()> {
+        self.structure.validate_move(id, parent)?;
+        self.tree
+            .check_limits(&self.structure, std::slice::from_ref(id), parent)?;
+        self.structure
+            .update_node(id, parent, index, &self.map, &mut self.txn)?;
+        self.structure.refresh_node(id, &self.map, &self.txn)
+    }
+}
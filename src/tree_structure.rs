@@ -1,22 +1,39 @@
 use std::{
-    collections::{BTreeSet, HashMap},
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap, HashSet},
     ops::{Deref, DerefMut},
     sync::Arc,
 };
 
 use fractional_index::FractionalIndex;
 use parking_lot::RwLock;
-use yrs::{block::Prelim, types::ToJson, Any, Map, MapPrelim, MapRef, Out};
+use yrs::{
+    block::Prelim,
+    types::{EntryChange, Event, Events, PathSegment, ToJson},
+    Any, BranchID, Map, MapPrelim, MapRef, Out, ID,
+};
+
+use crate::{
+    events::{DataChange, DataEvent, NodeEvent},
+    modification::Modification,
+    node::NodeId,
+    options::InsertTiebreak,
+    ConflictResolution, IntegrityError, RepairAction, Result, TreeError, TreeStats,
+};
 
-use crate::{node::NodeId, Result, TreeError};
+/// The key set on the container of a node that [`Tree::gc`](crate::Tree::gc) found
+/// unreachable, so that the next GC removes it if it's still unreachable.
+const GC_MARK_KEY: &str = "gc";
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct EdgeMap(HashMap<String, i64>);
 
 impl EdgeMap {
+    /// Returns the edge with the highest value. Concurrent moves can give two edges the
+    /// same value, so ties go to the highest parent ID, to agree on every client.
     pub fn max_edge(&self) -> Option<(String, i64)> {
         self.iter()
-            .max_by(|(_, a), (_, b)| a.cmp(b))
+            .max_by(|(id_a, a), (id_b, b)| a.cmp(b).then_with(|| id_a.cmp(id_b)))
             .map(|(id, edge)| (id.clone(), *edge))
     }
 
@@ -25,7 +42,7 @@ impl EdgeMap {
             .iter()
             .map(|(id, edge)| (id.clone(), *edge))
             .collect::<Vec<_>>();
-        edges.sort_by(|(_, a), (_, b)| b.cmp(a));
+        edges.sort_by(|(id_a, a), (id_b, b)| b.cmp(a).then_with(|| id_b.cmp(id_a)));
         edges
     }
 
@@ -67,6 +84,7 @@ pub struct NodeContainer {
     pub id: NodeId,
     pub edge_map: EdgeMap,
     pub fi: FractionalIndex,
+    pub created: Option<ID>,
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
@@ -76,12 +94,43 @@ pub struct TreeNode {
     pub children: Vec<NodeId>,
     pub fi: FractionalIndex,
     pub edge_map: EdgeMap,
+    /// The ID of the Yrs item that created the node's container.
+    pub created: Option<ID>,
+}
+
+/// The structure from before a change, as needed to find the change's node events.
+pub(crate) enum StructureBefore {
+    /// A copy of the whole structure.
+    Full(TreeStructure),
+    /// A copy of the given nodes and their ancestors, made when no other node can
+    /// change; see [`TreeStructure::affected_ids`].
+    Partial(TreeStructure, HashSet<NodeId>),
+}
+
+impl StructureBefore {
+    pub(crate) fn structure(&self) -> &TreeStructure {
+        match self {
+            StructureBefore::Full(structure) | StructureBefore::Partial(structure, _) => structure,
+        }
+    }
+
+    pub(crate) fn node_events(&self, new: &TreeStructure) -> Vec<NodeEvent> {
+        match self {
+            StructureBefore::Full(structure) => structure.node_events(new),
+            StructureBefore::Partial(structure, ids) => structure.node_events_for(new, ids),
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct TreeStructure {
     pub nodes: HashMap<NodeId, TreeNode>,
     pending_edge_map_updates: Vec<(NodeId, NodeId, i64)>,
+    /// The conflicts resolved when the structure was last built, in sorted order.
+    pub(crate) resolutions: Vec<ConflictResolution>,
+    /// Whether changes to a node record a [`Modification`] in its container.
+    pub(crate) track_modifications: bool,
+    pub(crate) insert_tiebreak: InsertTiebreak,
 }
 
 impl TreeStructure {
@@ -89,6 +138,9 @@ impl TreeStructure {
         Self {
             nodes: HashMap::new(),
             pending_edge_map_updates: Vec::new(),
+            resolutions: Vec::new(),
+            track_modifications: false,
+            insert_tiebreak: InsertTiebreak::default(),
         }
     }
 
@@ -104,6 +156,417 @@ impl TreeStructure {
         self.nodes.get(id)
     }
 
+    pub(crate) fn is_descendant_of(&self, id: &NodeId, ancestor: &NodeId) -> bool {
+        let mut current = self.get_parent(id);
+        while let Some(parent) = current {
+            if parent == ancestor {
+                return true;
+            }
+            current = self.get_parent(parent);
+        }
+        false
+    }
+
+    /// Returns the number of edges between the node and the root node.
+    pub(crate) fn depth(&self, id: &NodeId) -> usize {
+        let mut depth = 0;
+        let mut current = self.get_parent(id);
+        while let Some(parent) = current {
+            depth += 1;
+            current = self.get_parent(parent);
+        }
+        depth
+    }
+
+    /// Returns the deepest node that is an ancestor of both nodes, or is one of the
+    /// nodes itself. Nodes that aren't in the structure only have the root node as
+    /// a common ancestor.
+    pub(crate) fn lowest_common_ancestor(&self, a: &NodeId, b: &NodeId) -> NodeId {
+        let mut ancestors = HashSet::new();
+        let mut current = self.nodes.contains_key(a).then_some(a);
+        while let Some(id) = current {
+            ancestors.insert(id);
+            current = self.get_parent(id);
+        }
+
+        let mut current = b;
+        while !ancestors.contains(current) {
+            match self.get_parent(current) {
+                Some(parent) => current = parent,
+                None => return NodeId::Root,
+            }
+        }
+        current.clone()
+    }
+
+    /// Returns the number of edges on the longest path from the node down to a leaf.
+    pub(crate) fn height(&self, id: &NodeId) -> usize {
+        self.get_children(id)
+            .unwrap_or_default()
+            .iter()
+            .map(|child| self.height(child) + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns every node with its number of children, in depth-first order, which
+    /// is enough to rebuild the structure of the tree.
+    pub(crate) fn depth_first_shape(&self) -> Vec<(NodeId, usize)> {
+        let mut shape = vec![];
+        let mut stack = vec![&NodeId::Root];
+        while let Some(id) = stack.pop() {
+            let children = self.get_children(id).unwrap_or_default();
+            shape.push((id.clone(), children.len()));
+            stack.extend(children.iter().rev());
+        }
+        shape
+    }
+
+    /// Measures the structure in a single depth-first pass; see [`TreeStats`].
+    pub(crate) fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats::default();
+        let mut internal_count = 0;
+        // The number of nodes at each depth, starting at depth 1
+        let mut widths: Vec<usize> = vec![];
+
+        let mut stack = vec![(&NodeId::Root, 0)];
+        while let Some((id, depth)) = stack.pop() {
+            let children = self.get_children(id).unwrap_or_default();
+            if !children.is_empty() {
+                internal_count += 1;
+            }
+
+            if depth > 0 {
+                stats.node_count += 1;
+                stats.max_depth = stats.max_depth.max(depth);
+                if children.is_empty() {
+                    stats.leaf_count += 1;
+                }
+                if widths.len() < depth {
+                    widths.push(0);
+                }
+                widths[depth - 1] += 1;
+            }
+
+            stack.extend(children.iter().map(|child| (child, depth + 1)));
+        }
+
+        if internal_count > 0 {
+            stats.average_branching_factor = stats.node_count as f64 / internal_count as f64;
+        }
+        for (i, width) in widths.into_iter().enumerate() {
+            if width > stats.width_at_widest {
+                stats.widest_depth = i + 1;
+                stats.width_at_widest = width;
+            }
+        }
+
+        stats
+    }
+
+    /// Checks the structural invariants of the structure: every node other than the
+    /// root has a parent that lists it once, and no other node lists it; children
+    /// exist and are in order; and there are no cycles. Returns the violations
+    /// found, sorted by node ID.
+    pub(crate) fn check_integrity(&self) -> Vec<IntegrityError> {
+        let mut errors = vec![];
+        let mut listed_under: HashMap<&NodeId, Vec<NodeId>> = HashMap::new();
+
+        let mut ids = self.nodes.keys().collect::<Vec<_>>();
+        ids.sort();
+
+        for id in ids.iter().copied() {
+            let node = &self.nodes[id];
+            for child in node.children.iter() {
+                if self.nodes.contains_key(child) {
+                    listed_under.entry(child).or_default().push(id.clone());
+                } else {
+                    errors.push(IntegrityError::MissingChild {
+                        parent: id.clone(),
+                        child: child.clone(),
+                    });
+                }
+            }
+
+            let ordered = node.children.windows(2).all(|pair| {
+                match (self.nodes.get(&pair[0]), self.nodes.get(&pair[1])) {
+                    (Some(a), Some(b)) => self.sibling_order(a, b) == Ordering::Less,
+                    _ => true,
+                }
+            });
+            if !ordered {
+                errors.push(IntegrityError::Misordered(id.clone()));
+            }
+        }
+
+        for id in ids.iter().copied() {
+            if *id == NodeId::Root {
+                continue;
+            }
+
+            let listed = listed_under.remove(id).unwrap_or_default();
+            let parent = match self.get_parent(id) {
+                Some(parent) if self.nodes.contains_key(parent) => parent,
+                parent => {
+                    errors.push(IntegrityError::MissingParent {
+                        node: id.clone(),
+                        parent: parent.cloned(),
+                    });
+                    continue;
+                }
+            };
+
+            if listed.as_slice() != std::slice::from_ref(parent) {
+                errors.push(IntegrityError::ParentMismatch {
+                    node: id.clone(),
+                    parent: parent.clone(),
+                    listed_under: listed,
+                });
+            }
+
+            let mut visited = HashSet::from([id]);
+            let mut current = parent;
+            loop {
+                if !visited.insert(current) {
+                    if current == id {
+                        errors.push(IntegrityError::Cycle(id.clone()));
+                    }
+                    break;
+                }
+                match self.get_parent(current) {
+                    Some(parent) => current = parent,
+                    None => break,
+                }
+            }
+        }
+
+        errors
+    }
+
+    fn index_in_parent(&self, id: &NodeId, parent: &NodeId) -> usize {
+        self.get_children(parent)
+            .and_then(|children| children.iter().position(|child| child == id))
+            .unwrap_or_default()
+    }
+
+    /// Returns the structural events needed to get from this structure to `new`.
+    pub(crate) fn node_events(&self, new: &TreeStructure) -> Vec<NodeEvent> {
+        let ids = self
+            .nodes
+            .keys()
+            .chain(new.nodes.keys())
+            .collect::<HashSet<_>>();
+        self.node_events_for(new, ids)
+    }
+
+    /// Returns the structural events needed to get from this structure to `new` for
+    /// the given nodes only, so this structure only needs to hold those nodes and
+    /// their ancestors; see [`TreeStructure::subset`].
+    pub(crate) fn node_events_for<'a>(
+        &self,
+        new: &TreeStructure,
+        ids: impl IntoIterator<Item = &'a NodeId>,
+    ) -> Vec<NodeEvent> {
+        let mut events = vec![];
+
+        for id in ids {
+            let (node, new_node) = match (self.nodes.get(id), new.nodes.get(id)) {
+                (Some(node), Some(new_node)) => (node, new_node),
+                (Some(_), None) => {
+                    events.push(NodeEvent::Deleted(id.clone()));
+                    continue;
+                }
+                (None, Some(_)) => {
+                    events.push(NodeEvent::Created(id.clone()));
+                    continue;
+                }
+                (None, None) => continue,
+            };
+
+            if new_node.parent_id == node.parent_id && new_node.fi == node.fi {
+                continue;
+            }
+
+            if let (Some(old_parent), Some(new_parent)) = (&node.parent_id, &new_node.parent_id) {
+                events.push(NodeEvent::Moved {
+                    id: id.clone(),
+                    old_parent: old_parent.clone(),
+                    old_index: self.index_in_parent(id, old_parent),
+                    new_parent: new_parent.clone(),
+                    new_index: new.index_in_parent(id, new_parent),
+                });
+            }
+        }
+
+        events.sort_by(|a, b| a.id().cmp(b.id()));
+        events
+    }
+
+    /// Returns the nodes whose place in the tree can change when the containers of
+    /// the `touched` nodes change: those nodes and their descendants, and the nodes
+    /// placed by conflict resolutions that haven't been written to the document yet,
+    /// which go back to the place their containers give them once that place exists
+    /// again.
+    pub(crate) fn affected_ids(&self, touched: &HashSet<NodeId>) -> HashSet<NodeId> {
+        let mut affected = touched.clone();
+        let mut stack = touched.iter().collect::<Vec<_>>();
+        while let Some(id) = stack.pop() {
+            for child in self.get_children(id).unwrap_or_default() {
+                if affected.insert(child.clone()) {
+                    stack.push(child);
+                }
+            }
+        }
+
+        affected.extend(
+            self.pending_edge_map_updates
+                .iter()
+                .map(|(id, _, _)| id.clone()),
+        );
+        affected
+    }
+
+    /// Returns a copy of the given nodes and their ancestors, which is all
+    /// [`TreeStructure::node_events_for`] needs of the structure from before a change
+    /// to describe the change to those nodes.
+    pub(crate) fn subset(&self, ids: &HashSet<NodeId>) -> TreeStructure {
+        let mut subset = TreeStructure::new();
+        for id in ids {
+            let mut current = Some(id);
+            while let Some(id) = current {
+                if subset.nodes.contains_key(id) {
+                    break;
+                }
+                let Some(node) = self.nodes.get(id) else {
+                    break;
+                };
+                subset.nodes.insert(id.clone(), node.clone());
+                current = node.parent_id.as_ref();
+            }
+        }
+        subset
+    }
+
+    /// Returns the IDs of the nodes whose containers the given Yrs events on the
+    /// tree's map add, remove, or change.
+    pub(crate) fn touched_ids(txn: &yrs::TransactionMut, events: &Events) -> HashSet<NodeId> {
+        let mut ids = HashSet::new();
+        for event in events.iter() {
+            match event.path().front() {
+                Some(PathSegment::Key(id)) => {
+                    ids.insert(NodeId::from(id.as_ref()));
+                }
+                Some(PathSegment::Index(_)) => {}
+                None => {
+                    if let Event::Map(e) = event {
+                        ids.extend(e.keys(txn).keys().map(|id| NodeId::from(id.as_ref())));
+                    }
+                }
+            }
+        }
+        ids
+    }
+
+    /// Returns the changes to node data made by the given Yrs events, grouped by node.
+    pub(crate) fn data_events(
+        txn: &yrs::TransactionMut,
+        events: &Events,
+        map: &MapRef,
+    ) -> Vec<DataEvent> {
+        let mut data_events: Vec<DataEvent> = vec![];
+
+        for event in events.iter() {
+            let path = event.path();
+            let (id, changes) = match (path.front(), path.get(1), path.get(2)) {
+                // Keys set or removed on a node's data map
+                (Some(PathSegment::Key(id)), Some(PathSegment::Key(data)), None)
+                    if data.as_ref() == "data" =>
+                {
+                    let Event::Map(e) = event else {
+                        continue;
+                    };
+                    let changes = e
+                        .keys(txn)
+                        .iter()
+                        .map(|(key, change)| DataChange::from_entry_change(key, change))
+                        .collect::<Vec<_>>();
+                    (id, changes)
+                }
+                // Changes inside a shared type stored on a node's data map, however
+                // deeply it's nested
+                (
+                    Some(PathSegment::Key(id)),
+                    Some(PathSegment::Key(data)),
+                    Some(PathSegment::Key(key)),
+                ) if data.as_ref() == "data" => {
+                    let change = DataChange {
+                        key: key.to_string(),
+                        old_value: None,
+                        new_value: Self::get_data_value(map, txn, id, key),
+                        path: path.iter().skip(3).cloned().collect(),
+                    };
+                    (id, vec![change])
+                }
+                // The data map itself was created or replaced on the node's container
+                (Some(PathSegment::Key(id)), None, None) => {
+                    let Event::Map(e) = event else {
+                        continue;
+                    };
+                    let Some(
+                        EntryChange::Inserted(Out::YMap(data))
+                        | EntryChange::Updated(_, Out::YMap(data)),
+                    ) = e.keys(txn).get("data")
+                    else {
+                        continue;
+                    };
+                    let changes = data
+                        .iter(txn)
+                        .map(|(key, value)| DataChange {
+                            key: key.to_string(),
+                            old_value: None,
+                            new_value: Some(value),
+                            path: vec![],
+                        })
+                        .collect::<Vec<_>>();
+                    (id, changes)
+                }
+                _ => continue,
+            };
+
+            let id = NodeId::from(id.as_ref());
+            match data_events.iter_mut().find(|e| e.id == id) {
+                Some(data_event) => {
+                    for change in changes {
+                        if !data_event
+                            .changes
+                            .iter()
+                            .any(|c| c.key == change.key && c.path == change.path)
+                        {
+                            data_event.changes.push(change);
+                        }
+                    }
+                }
+                None => data_events.push(DataEvent { id, changes }),
+            }
+        }
+
+        for data_event in data_events.iter_mut() {
+            data_event.changes.sort_by(|a, b| a.key.cmp(&b.key));
+        }
+        data_events.retain(|e| !e.changes.is_empty());
+        data_events
+    }
+
+    fn get_data_value(map: &MapRef, txn: &yrs::TransactionMut, id: &str, key: &str) -> Option<Out> {
+        let Some(Out::YMap(container)) = map.get(txn, id) else {
+            return None;
+        };
+        let Some(Out::YMap(data)) = container.get(txn, "data") else {
+            return None;
+        };
+        data.get(txn, key)
+    }
+
     pub(crate) fn delete_nodes(
         &mut self,
         ids: &[NodeId],
@@ -115,21 +578,114 @@ impl TreeStructure {
         }
 
         for id in ids {
-            map.remove(txn, &id.to_string());
+            map.remove(txn, id.as_ref());
         }
 
         Ok(())
     }
 
+    /// Re-reads a node's position from the Yjs map. This keeps a copy of the structure
+    /// up to date with the changes made in a transaction that hasn't been committed
+    /// yet; the tree's own structure is rebuilt once the transaction is committed.
+    pub(crate) fn refresh_node(
+        &mut self,
+        id: &NodeId,
+        map: &MapRef,
+        txn: &yrs::TransactionMut,
+    ) -> Result<()> {
+        let Some(Out::YMap(container)) = map.get(txn, id.as_ref()) else {
+            return Err(TreeError::BadYrsDoc(format!(
+                "Node container for node {} not found",
+                id
+            )));
+        };
+
+        let edge_map: HashMap<String, i64> = container.get_as(txn, "em").unwrap_or_default();
+        let edge_map: EdgeMap = edge_map.into();
+        let fi_str: String = container.get_as(txn, "fi").unwrap_or_default();
+        let fi = FractionalIndex::from_string(&fi_str).unwrap_or_default();
+        let Some((parent_id, _)) = edge_map.max_edge() else {
+            return Err(TreeError::BadYrsDoc(format!(
+                "No parent set for node: {}",
+                id
+            )));
+        };
+        let parent_id = NodeId::from(parent_id);
+        let created = Self::container_id(&container);
+
+        let children = match self.nodes.get(id) {
+            Some(node) => node.children.clone(),
+            None => vec![],
+        };
+        self.detach_node(id);
+        self.nodes.insert(
+            id.clone(),
+            TreeNode {
+                id: id.clone(),
+                parent_id: Some(parent_id.clone()),
+                children,
+                fi,
+                edge_map,
+                created,
+            },
+        );
+
+        let node = &self.nodes[id];
+        let siblings = self.get_children(&parent_id).unwrap_or_default();
+        let position = siblings.partition_point(|sibling| {
+            self.sibling_order(&self.nodes[sibling], node) == Ordering::Less
+        });
+        if let Some(parent) = self.nodes.get_mut(&parent_id) {
+            parent.children.insert(position, id.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Removes a node from the structure, without touching the Yjs map.
+    pub(crate) fn remove_node(&mut self, id: &NodeId) {
+        self.detach_node(id);
+        self.nodes.remove(id);
+    }
+
+    /// Moves a node to `index` among the children of `parent` in the structure only,
+    /// without touching the Yjs map or the node's fractional index. Used to check a
+    /// series of moves against the structure they would produce.
+    pub(crate) fn place_node(&mut self, id: &NodeId, parent: &NodeId, index: Option<usize>) {
+        self.detach_node(id);
+        if let Some(node) = self.nodes.get_mut(id) {
+            node.parent_id = Some(parent.clone());
+        }
+        if let Some(parent) = self.nodes.get_mut(parent) {
+            let index = index
+                .unwrap_or(parent.children.len())
+                .min(parent.children.len());
+            parent.children.insert(index, id.clone());
+        }
+    }
+
+    fn detach_node(&mut self, id: &NodeId) {
+        let Some(parent_id) = self.get_parent(id).cloned() else {
+            return;
+        };
+        if let Some(parent) = self.nodes.get_mut(&parent_id) {
+            parent.children.retain(|child| child != id);
+        }
+    }
+
     pub(crate) fn init_from_yjs(&mut self, map: &MapRef, txn: &yrs::TransactionMut) -> Result<()> {
         // Clear nodes in case of re-initialization due to large Yjs updates
         self.nodes.clear();
+        self.resolutions.clear();
+        // Any edges still waiting to be written are recalculated from the new state
+        self.pending_edge_map_updates.clear();
 
         let containers = Self::collect_node_containers(map, txn);
         self.create_initial_nodes(&containers);
         let non_attached_nodes = self.process_parent_relationships(&containers)?;
         self.reattach_nodes(non_attached_nodes)?;
         self.update_children_order();
+        self.resolutions.sort();
 
         Ok(())
     }
@@ -149,7 +705,7 @@ impl TreeStructure {
         map: &MapRef,
         id: &NodeId,
     ) -> Result<MapRef> {
-        let container = map.get(txn, &id.to_string()).unwrap();
+        let container = map.get(txn, id.as_ref()).unwrap();
         let yrs::Out::YMap(container) = container else {
             return Err(
                 TreeError::BadYrsDoc(format!("Node container for node {} not found", id)).into(),
@@ -162,6 +718,9 @@ impl TreeStructure {
     fn collect_node_containers(map: &MapRef, txn: &yrs::TransactionMut) -> Vec<NodeContainer> {
         let mut containers = Vec::new();
         for (id, out) in map.iter(txn) {
+            if id == NodeId::Root.to_string() {
+                continue;
+            }
             if let yrs::Out::YMap(container) = out {
                 let edge_map: HashMap<String, i64> =
                     container.get_as(txn, "em").unwrap_or_default();
@@ -171,6 +730,7 @@ impl TreeStructure {
                     id: id.into(),
                     edge_map: edge_map.into(),
                     fi,
+                    created: Self::container_id(&container),
                 });
             }
         }
@@ -184,6 +744,7 @@ impl TreeStructure {
             children: vec![],
             fi: FractionalIndex::default(),
             edge_map: EdgeMap::default(),
+            created: None,
         };
         self.nodes.insert(NodeId::Root, root);
 
@@ -196,6 +757,7 @@ impl TreeStructure {
                 children: vec![],
                 fi: fi.clone(),
                 edge_map: container.edge_map.clone(),
+                created: container.created,
             };
             self.nodes.insert(id.clone(), node);
         }
@@ -217,12 +779,15 @@ impl TreeStructure {
                 };
                 node.parent_id = Some(parent_id);
             } else {
-                return Err(
-                    TreeError::BadYrsDoc(format!("No parent set for node: {}", node.id)).into(),
-                );
+                // Clients never write a node without a parent, so the document is
+                // corrupt; leave the node out of the tree until it's repaired
+                self.nodes.remove(&node.id);
             }
         }
 
+        self.reparent_moved_orphans();
+        self.remove_orphaned_nodes();
+
         let mut non_attached_nodes = BTreeSet::new();
         for node in self.nodes.values() {
             if !self.can_reach(&node.id, &NodeId::Root) {
@@ -233,35 +798,144 @@ impl TreeStructure {
         Ok(non_attached_nodes)
     }
 
-    fn reattach_nodes(&mut self, mut non_attached_nodes: BTreeSet<NodeId>) -> Result<()> {
-        while !non_attached_nodes.is_empty() {
-            // find the historical parent with the highest edge value
-            // that is also not inside the non_attached_nodes set
-            let next = non_attached_nodes.first().unwrap().clone();
-            if self.can_reach(&next, &NodeId::Root) {
-                non_attached_nodes.remove(&next);
+    /// Moves nodes whose parent no longer exists back to the most recent of their
+    /// previous parents that does. This happens when a client deletes a node without
+    /// knowing that another client moved a node under it.
+    fn reparent_moved_orphans(&mut self) {
+        let mut orphaned = self
+            .nodes
+            .values()
+            .filter(|node| {
+                node.parent_id
+                    .as_ref()
+                    .is_some_and(|parent| !self.nodes.contains_key(parent))
+            })
+            .map(|node| node.id.clone())
+            .collect::<Vec<_>>();
+        // Sort so the pending edge map updates are the same on every client
+        orphaned.sort();
+
+        for id in orphaned {
+            let node = &self.nodes[&id];
+            let deleted_parent = node.parent_id.clone().unwrap();
+            let new_parent = node
+                .edge_map
+                .edges_desc()
+                .into_iter()
+                .map(|(parent, _)| NodeId::from(parent))
+                .find(|parent| self.nodes.contains_key(parent));
+            let Some(new_parent) = new_parent else {
                 continue;
+            };
+
+            let node = self.nodes.get_mut(&id).unwrap();
+            node.parent_id = Some(new_parent.clone());
+            let (edge_id, edge_val) = node.edge_map.add_edge(new_parent.as_ref());
+            self.pending_edge_map_updates
+                .push((id.clone(), edge_id.into(), edge_val));
+            self.resolutions.push(ConflictResolution::MovedIntoDeleted {
+                node: id,
+                deleted_parent,
+                new_parent,
+            });
+        }
+    }
+
+    /// Removes nodes whose parent, or one of whose ancestors, no longer exists. This
+    /// happens when a client deletes a node (or undoes its creation) without knowing
+    /// about a child another client added to it; like any other descendant of a
+    /// deleted node, the child is deleted along with it.
+    fn remove_orphaned_nodes(&mut self) {
+        let orphaned = self
+            .nodes
+            .keys()
+            .filter(|id| self.has_missing_ancestor(id))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for id in orphaned.iter() {
+            let moved = self.resolutions.iter().position(|resolution| {
+                matches!(resolution, ConflictResolution::MovedIntoDeleted { node, .. } if node == id)
+            });
+            let deleted_parent = match moved.map(|i| self.resolutions.remove(i)) {
+                // The node was moved back under a node that is being removed as well
+                Some(ConflictResolution::MovedIntoDeleted { deleted_parent, .. }) => deleted_parent,
+                _ => {
+                    let parent = self.nodes[id].parent_id.clone().unwrap();
+                    // Only report the nodes whose own parent is gone
+                    if self.nodes.contains_key(&parent) {
+                        continue;
+                    }
+                    parent
+                }
+            };
+            self.resolutions
+                .push(ConflictResolution::RemovedWithParent {
+                    node: id.clone(),
+                    deleted_parent,
+                });
+        }
+
+        self.pending_edge_map_updates
+            .retain(|(id, _, _)| !orphaned.contains(id));
+        for id in orphaned {
+            self.nodes.remove(&id);
+        }
+    }
+
+    fn has_missing_ancestor(&self, id: &NodeId) -> bool {
+        let mut visited = HashSet::new();
+        let mut current = id;
+        while let Some(parent) = self.nodes.get(current).and_then(|n| n.parent_id.as_ref()) {
+            if !self.nodes.contains_key(parent) {
+                return true;
+            }
+            // Cycles are resolved when the nodes are reattached
+            if !visited.insert(parent) {
+                return false;
             }
+            current = parent;
+        }
+        false
+    }
 
-            let node = self.nodes.get_mut(&next).unwrap();
-            let edges_desc = &node.edge_map.edges_desc();
-            let first_valid_parent = edges_desc
+    fn reattach_nodes(&mut self, mut non_attached_nodes: BTreeSet<NodeId>) -> Result<()> {
+        loop {
+            non_attached_nodes.retain(|id| !self.can_reach(id, &NodeId::Root));
+            if non_attached_nodes.is_empty() {
+                break;
+            }
+
+            // find a node with a historical parent (the one with the highest edge
+            // value) that is not inside the non_attached_nodes set; if every node's
+            // previous parents are detached too, move the first node to the root
+            let (next, parent_id) = non_attached_nodes
                 .iter()
-                .find(|(id, _)| !non_attached_nodes.contains(&id.into()));
-
-            if let Some((parent_id, _)) = first_valid_parent {
-                node.parent_id = Some(parent_id.into());
-                let (edge_id, edge_val) = node.edge_map.add_edge(parent_id);
-                self.pending_edge_map_updates
-                    .push((node.id.clone(), edge_id.into(), edge_val));
-                non_attached_nodes.remove(&next);
-            } else {
-                return Err(TreeError::BadYrsDoc(format!(
-                    "No valid parent found for detached node: {}",
-                    next
-                ))
-                .into());
+                .find_map(|id| {
+                    let edges_desc = self.nodes.get(id).unwrap().edge_map.edges_desc();
+                    edges_desc
+                        .into_iter()
+                        .map(|(parent_id, _)| NodeId::from(parent_id))
+                        .find(|parent_id| {
+                            !non_attached_nodes.contains(parent_id)
+                                && self.nodes.contains_key(parent_id)
+                        })
+                        .map(|parent_id| (id.clone(), parent_id))
+                })
+                .unwrap_or_else(|| (non_attached_nodes.first().unwrap().clone(), NodeId::Root));
+
+            let node = self.nodes.get_mut(&next).unwrap();
+            if let Some(kept) = node.parent_id.replace(parent_id.clone()) {
+                self.resolutions.push(ConflictResolution::CycleBroken {
+                    kept,
+                    reparented: next.clone(),
+                    new_parent: parent_id.clone(),
+                });
             }
+            let (edge_id, edge_val) = node.edge_map.add_edge(parent_id.as_ref());
+            self.pending_edge_map_updates
+                .push((node.id.clone(), edge_id.into(), edge_val));
+            non_attached_nodes.remove(&next);
         }
 
         Ok(())
@@ -286,20 +960,36 @@ impl TreeStructure {
         for node_id in all_node_ids.iter() {
             let node = self.nodes.get(node_id).unwrap();
             let mut children = node.children.clone();
-            children.sort_by(|a, b| {
-                let node_a = self.nodes.get(a).unwrap();
-                let node_b = self.nodes.get(b).unwrap();
-
-                match node_a.fi.cmp(&node_b.fi) {
-                    std::cmp::Ordering::Equal => node_a.id.cmp(&node_b.id),
-                    ordering => ordering,
-                }
-            });
+            children.sort_by(|a, b| self.sibling_order(&self.nodes[a], &self.nodes[b]));
             let node = self.nodes.get_mut(node_id).unwrap();
             node.children = children;
         }
     }
 
+    /// Compares two siblings by fractional index, breaking ties according to the
+    /// structure's [`InsertTiebreak`] policy, and finally by ID.
+    fn sibling_order(&self, a: &TreeNode, b: &TreeNode) -> Ordering {
+        a.fi.cmp(&b.fi)
+            .then_with(|| match self.insert_tiebreak {
+                InsertTiebreak::ByNodeId => Ordering::Equal,
+                InsertTiebreak::Insertion => match (a.created, b.created) {
+                    (Some(a), Some(b)) => {
+                        a.client.cmp(&b.client).then_with(|| b.clock.cmp(&a.clock))
+                    }
+                    _ => Ordering::Equal,
+                },
+            })
+            .then_with(|| a.id.cmp(&b.id))
+    }
+
+    /// Returns the ID of the Yrs item that created the given node container.
+    fn container_id(container: &MapRef) -> Option<ID> {
+        match container.as_ref().id() {
+            BranchID::Nested(id) => Some(id),
+            BranchID::Root(_) => None,
+        }
+    }
+
     fn can_reach(&self, id: &NodeId, target: &NodeId) -> bool {
         let mut tortoise = id;
         let mut hare = match self.nodes.get(id).and_then(|n| n.parent_id.as_ref()) {
@@ -369,30 +1059,8 @@ impl TreeStructure {
                 .unwrap_or_default()
         };
 
-        if let Some(node) = self.nodes.get_mut(id) {
-            // We should calculate our updated edge value from the node's edge map
-            // since we might have updated it during the node reattachment phase
-            // without updating the backing Yjs map
-            let node_edge_map = &mut node.edge_map;
-            let (_, new_edge) = node_edge_map.add_edge(&parent.to_string());
-            node.fi = new_fi.clone();
-
-            let Some(Out::YMap(container)) = map.get(txn, &id.to_string()) else {
-                return Err(TreeError::BadYrsDoc(format!(
-                    "Node container for node {} not found",
-                    id
-                ))
-                .into());
-            };
-
-            let Some(Out::YMap(edge_map)) = container.get(txn, "em") else {
-                return Err(
-                    TreeError::BadYrsDoc(format!("Edge map for node {} not found", id)).into(),
-                );
-            };
-
-            edge_map.insert(txn, parent.to_string(), new_edge);
-            container.insert(txn, "fi", new_fi.to_string());
+        if self.nodes.contains_key(id) {
+            self.set_position(id, parent, new_fi, map, txn)?;
         } else {
             // No existing node; we need to create the container and the node data
             let container = map.insert(txn, id.to_string(), MapPrelim::default());
@@ -400,12 +1068,191 @@ impl TreeStructure {
 
             edge_map.insert(txn, parent.to_string(), 0);
             container.insert(txn, "fi", new_fi.to_string());
+            self.record_modification(&container, txn);
+        }
+
+        Ok(())
+    }
+
+    /// Swaps the positions of two existing nodes, giving each the other's parent
+    /// and fractional index.
+    pub(crate) fn swap_nodes(
+        &mut self,
+        a: &NodeId,
+        b: &NodeId,
+        map: &MapRef,
+        txn: &mut yrs::TransactionMut,
+    ) -> Result<()> {
+        let (Some(node_a), Some(node_b)) = (self.nodes.get(a), self.nodes.get(b)) else {
+            let missing = if self.nodes.contains_key(a) { b } else { a };
+            return Err(TreeError::InvalidTarget(missing.clone()));
+        };
+
+        let (Some(parent_a), Some(parent_b)) = (node_a.parent_id.clone(), node_b.parent_id.clone())
+        else {
+            return Err(TreeError::InvalidTarget(NodeId::Root));
+        };
+        let (fi_a, fi_b) = (node_a.fi.clone(), node_b.fi.clone());
+
+        self.set_position(a, &parent_b, fi_b, map, txn)?;
+        self.set_position(b, &parent_a, fi_a, map, txn)
+    }
+
+    /// Checks that a node moved to `index` among the parent's children would end up
+    /// there, which is the case for any index up to the number of children.
+    pub(crate) fn check_index(&self, parent: &NodeId, index: Option<usize>) -> Result<()> {
+        match (index, self.get_children(parent)) {
+            (Some(index), Some(children)) if index > children.len() => {
+                Err(TreeError::IndexOutOfRange(parent.clone(), index))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that `id` can be placed under `parent`: the parent must exist, and must
+    /// not be the node itself or one of its descendants. `id` doesn't need to exist yet.
+    pub(crate) fn validate_move(&self, id: &NodeId, parent: &NodeId) -> Result<()> {
+        if !self.nodes.contains_key(parent) {
+            return Err(TreeError::MissingParent(parent.clone()));
+        }
+
+        if id == parent || self.is_descendant_of(parent, id) {
+            return Err(TreeError::Cycle(id.clone(), parent.clone()));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that moving (or creating) the given nodes under `parent` stays within
+    /// the given depth and child count limits.
+    pub(crate) fn check_limits(
+        &self,
+        ids: &[NodeId],
+        parent: &NodeId,
+        max_depth: Option<usize>,
+        max_children: Option<usize>,
+    ) -> Result<()> {
+        if let Some(max) = max_children {
+            let children = self.get_children(parent).unwrap_or_default();
+            let mut added: Vec<&NodeId> = vec![];
+            for id in ids {
+                if !children.contains(id) && !added.contains(&id) {
+                    added.push(id);
+                }
+            }
+            if children.len() + added.len() > max {
+                return Err(TreeError::MaxChildrenExceeded(parent.clone(), max));
+            }
+        }
+
+        if let Some(max) = max_depth {
+            let depth = self.depth(parent) + 1;
+            for id in ids {
+                if depth + self.height(id) > max {
+                    return Err(TreeError::MaxDepthExceeded(id.clone(), max));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Moves the given nodes under `parent`, placing them next to each other in the
+    /// given order. `index` is a position among the parent's children that are not
+    /// being moved.
+    pub(crate) fn move_nodes(
+        &mut self,
+        ids: &[NodeId],
+        parent: &NodeId,
+        index: Option<usize>,
+        map: &MapRef,
+        txn: &mut yrs::TransactionMut,
+    ) -> Result<()> {
+        for id in ids {
+            if *id == NodeId::Root || !self.nodes.contains_key(id) {
+                return Err(TreeError::InvalidTarget(id.clone()));
+            }
+
+            self.validate_move(id, parent)?;
+        }
+
+        let siblings = self
+            .get_children(parent)
+            .unwrap_or_default()
+            .iter()
+            .filter(|child| !ids.contains(child))
+            .collect::<Vec<_>>();
+        let index = index.unwrap_or(siblings.len()).min(siblings.len());
+
+        let mut lower = index
+            .checked_sub(1)
+            .and_then(|i| self.nodes.get(siblings[i]))
+            .map(|node| node.fi.clone());
+        let upper = siblings
+            .get(index)
+            .and_then(|id| self.nodes.get(*id))
+            .map(|node| node.fi.clone());
+
+        let mut moved = vec![];
+        for id in ids {
+            if moved.contains(&id) {
+                continue;
+            }
+            moved.push(id);
+
+            let fi = FractionalIndex::new(lower.as_ref(), upper.as_ref()).unwrap_or_else(|| {
+                // The neighbors share a fractional index, so there's no room between them
+                lower
+                    .as_ref()
+                    .map(FractionalIndex::new_after)
+                    .unwrap_or_default()
+            });
+            self.set_position(id, parent, fi.clone(), map, txn)?;
+            lower = Some(fi);
         }
 
         Ok(())
     }
 
-    pub(crate) fn set_data<V: Prelim + Into<Any>>(
+    /// Attaches an existing node to `parent` at fractional index `fi`, in both the
+    /// cached structure and the Yjs map.
+    fn set_position(
+        &mut self,
+        id: &NodeId,
+        parent: &NodeId,
+        fi: FractionalIndex,
+        map: &MapRef,
+        txn: &mut yrs::TransactionMut,
+    ) -> Result<()> {
+        let Some(node) = self.nodes.get_mut(id) else {
+            return Err(TreeError::InvalidTarget(id.clone()));
+        };
+
+        // We should calculate our updated edge value from the node's edge map
+        // since we might have updated it during the node reattachment phase
+        // without updating the backing Yjs map
+        let node_edge_map = &mut node.edge_map;
+        let (_, new_edge) = node_edge_map.add_edge(parent.as_ref());
+        node.fi = fi.clone();
+
+        let Some(Out::YMap(container)) = map.get(txn, id.as_ref()) else {
+            return Err(
+                TreeError::BadYrsDoc(format!("Node container for node {} not found", id)).into(),
+            );
+        };
+
+        let Some(Out::YMap(edge_map)) = container.get(txn, "em") else {
+            return Err(TreeError::BadYrsDoc(format!("Edge map for node {} not found", id)).into());
+        };
+
+        edge_map.insert(txn, parent.to_string(), new_edge);
+        container.insert(txn, "fi", fi.to_string());
+        self.record_modification(&container, txn);
+
+        Ok(())
+    }
+
+    pub(crate) fn set_data<V: Prelim>(
         &mut self,
         id: &NodeId,
         key: &str,
@@ -431,16 +1278,57 @@ impl TreeStructure {
         };
 
         let result = data_map.insert(txn, key, value);
+        self.record_modification(&yrs_map, txn);
 
         Ok(result)
     }
 
-    pub(crate) fn get_data(
+    /// Removes the value stored on the node at the given key, if there is one.
+    pub(crate) fn remove_data(
+        &mut self,
+        id: &NodeId,
+        key: &str,
+        map: &MapRef,
+        txn: &mut yrs::TransactionMut,
+    ) -> Result<()> {
+        let yrs_map = self.get_yrs_map_for_node(txn, map, id)?;
+        if let Some(Out::YMap(data_map)) = yrs_map.get(txn, "data") {
+            if data_map.remove(txn, key).is_some() {
+                self.record_modification(&yrs_map, txn);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records that this client changed the node with the given container just now,
+    /// if modifications are being tracked.
+    fn record_modification(&self, container: &MapRef, txn: &mut yrs::TransactionMut) {
+        if self.track_modifications {
+            let modification = Modification::now(txn.doc().client_id());
+            container.insert(txn, "lm", modification.to_any());
+        }
+    }
+
+    /// Returns the last recorded modification of the node, if there is one.
+    pub(crate) fn last_modified<T: yrs::ReadTxn>(
+        &self,
+        id: &NodeId,
+        map: &MapRef,
+        txn: &T,
+    ) -> Option<Modification> {
+        let Some(Out::YMap(container)) = map.get(txn, id.as_ref()) else {
+            return None;
+        };
+        container.get(txn, "lm").and_then(Modification::from_out)
+    }
+
+    pub(crate) fn get_data<T: yrs::ReadTxn>(
         &self,
         id: &NodeId,
         key: &str,
         map: &MapRef,
-        txn: &mut yrs::Transaction,
+        txn: &T,
     ) -> Result<Option<yrs::Out>> {
         let Ok(yrs_map) = self.get_yrs_map_for_node(txn, map, id) else {
             return Err(
@@ -471,6 +1359,32 @@ impl TreeStructure {
         }
     }
 
+    /// Returns all data stored on the node, keyed by data key.
+    pub(crate) fn get_all_data<T: yrs::ReadTxn>(
+        &self,
+        id: &NodeId,
+        map: &MapRef,
+        txn: &T,
+    ) -> Result<HashMap<String, Any>> {
+        let Some(Out::YMap(container)) = map.get(txn, id.as_ref()) else {
+            return Err(TreeError::TreePoisoned(Box::new(TreeError::BadYrsDoc(
+                format!("Container for node {} not found", id),
+            ))));
+        };
+
+        match container.get(txn, "data") {
+            Some(Out::YMap(data_map)) => Ok(data_map
+                .iter(txn)
+                .map(|(key, value)| (key.to_string(), value.to_json(txn)))
+                .collect()),
+            Some(_) => Err(TreeError::TreePoisoned(Box::new(TreeError::BadYrsDoc(
+                format!("Data container for node {} is not a map", id),
+            )))),
+            // No data set yet
+            None => Ok(HashMap::new()),
+        }
+    }
+
     pub(crate) fn get_data_as<V: serde::de::DeserializeOwned>(
         &self,
         id: &NodeId,
@@ -478,16 +1392,221 @@ impl TreeStructure {
         map: &MapRef,
         txn: &mut yrs::Transaction,
     ) -> Result<V> {
-        let any = match self.get_data(id, key, map, txn)? {
+        let any = match self.get_data(id, key, map, &*txn)? {
             Some(any) => any,
             None => yrs::Out::Any(yrs::Any::Null),
         };
         let json = any.to_json(txn);
-        yrs::encoding::serde::from_any(&json).map_err(|e| {
-            TreeError::BadYrsDoc(format!("Error deserializing data for node {}: {}", id, e)).into()
+        yrs::encoding::serde::from_any(&json).map_err(|e| TreeError::DeserializationFailed {
+            key: key.to_string(),
+            expected_type: std::any::type_name::<V>(),
+            source: Arc::new(e),
         })
     }
 
+    /// Marks the containers of nodes that aren't part of the structure for removal,
+    /// removes the ones an earlier GC already marked, and removes the edges of the
+    /// nodes in the structure that point to nodes that don't exist. Marks left on
+    /// nodes that are part of the structure again are cleared. Returns how many
+    /// containers were removed and marked, and how many edges were removed.
+    pub(crate) fn gc(&self, map: &MapRef, txn: &mut yrs::TransactionMut) -> (usize, usize, usize) {
+        let mut nodes_removed = 0;
+        let mut nodes_marked = 0;
+        let mut edges_removed = 0;
+
+        let keys = map.keys(txn).map(String::from).collect::<Vec<_>>();
+        for key in keys {
+            let Some(Out::YMap(container)) = map.get(txn, &key) else {
+                continue;
+            };
+            let marked = container.contains_key(txn, GC_MARK_KEY);
+
+            if !self.nodes.contains_key(&NodeId::from(key.as_str())) {
+                // The node's parent history is kept, since that's what lets another
+                // client make it reachable again before the next GC
+                if marked {
+                    map.remove(txn, &key);
+                    nodes_removed += 1;
+                } else {
+                    container.insert(txn, GC_MARK_KEY, true);
+                    nodes_marked += 1;
+                }
+                continue;
+            }
+
+            if marked {
+                container.remove(txn, GC_MARK_KEY);
+            }
+            let Some(Out::YMap(edge_map)) = container.get(txn, "em") else {
+                continue;
+            };
+            // The node's current parent is in the structure, so it's never removed
+            let stale = edge_map
+                .keys(txn)
+                .filter(|parent| !self.nodes.contains_key(&NodeId::from(*parent)))
+                .map(String::from)
+                .collect::<Vec<_>>();
+            for parent in stale {
+                edge_map.remove(txn, &parent);
+                edges_removed += 1;
+            }
+        }
+
+        (nodes_removed, nodes_marked, edges_removed)
+    }
+
+    /// Rewrites the parts of the document that don't describe a valid tree; see
+    /// [`Tree::repair`](crate::Tree::repair). Returns the structure of the repaired
+    /// document, with siblings ordered by `insert_tiebreak`, along with what was
+    /// changed.
+    pub(crate) fn repair(
+        map: &MapRef,
+        txn: &mut yrs::TransactionMut,
+        lost_and_found: &NodeId,
+        insert_tiebreak: InsertTiebreak,
+    ) -> Result<(Self, Vec<RepairAction>)> {
+        let mut actions = vec![];
+        let root_key = NodeId::Root.to_string();
+
+        let mut ids = vec![];
+        let mut entries = map
+            .iter(txn)
+            .map(|(key, out)| (key.to_string(), matches!(out, Out::YMap(_))))
+            .collect::<Vec<_>>();
+        entries.sort();
+        for (key, is_container) in entries {
+            if is_container && key != root_key {
+                ids.push(key);
+            } else {
+                map.remove(txn, &key);
+                actions.push(RepairAction::RemovedEntry { key });
+            }
+        }
+        let mut existing = ids.iter().cloned().collect::<HashSet<_>>();
+        existing.insert(root_key.clone());
+
+        let mut reindex = BTreeSet::new();
+        for id in ids.iter() {
+            let node = NodeId::from(id);
+            let Some(Out::YMap(container)) = map.get(txn, id) else {
+                continue;
+            };
+
+            let edge_map = match container.get(txn, "em") {
+                Some(Out::YMap(edge_map)) => edge_map,
+                _ => container.insert(txn, "em", MapPrelim::default()),
+            };
+            let mut edges = EdgeMap::default();
+            let mut invalid = vec![];
+            for (parent, value) in edge_map.iter(txn) {
+                let value = match value {
+                    Out::Any(Any::BigInt(value)) => Some(value),
+                    Out::Any(Any::Number(value)) if value.fract() == 0.0 => Some(value as i64),
+                    _ => None,
+                };
+                match value {
+                    Some(value) if parent != id && existing.contains(parent) => {
+                        edges.insert(parent.to_string(), value);
+                    }
+                    _ => invalid.push(parent.to_string()),
+                }
+            }
+            invalid.sort();
+            for parent in invalid {
+                edge_map.remove(txn, &parent);
+                actions.push(RepairAction::RemovedEdge {
+                    node: node.clone(),
+                    parent: parent.into(),
+                });
+            }
+
+            if let Some((kept, edge)) = edges.max_edge() {
+                let mut dropped = edges
+                    .iter()
+                    .filter(|(parent, value)| **value == edge && **parent != kept)
+                    .map(|(parent, _)| NodeId::from(parent))
+                    .collect::<Vec<_>>();
+                if !dropped.is_empty() {
+                    dropped.sort();
+                    edge_map.insert(txn, kept.clone(), edge + 1);
+                    actions.push(RepairAction::DeduplicatedParent {
+                        node: node.clone(),
+                        kept: kept.into(),
+                        dropped,
+                    });
+                }
+            } else {
+                let parent = if *lost_and_found == node {
+                    NodeId::Root
+                } else {
+                    lost_and_found.clone()
+                };
+                if !existing.contains(parent.as_ref()) {
+                    let container = map.insert(txn, parent.to_string(), MapPrelim::default());
+                    let edge_map = container.insert(txn, "em", MapPrelim::default());
+                    edge_map.insert(txn, root_key.clone(), 0);
+                    existing.insert(parent.to_string());
+                    reindex.insert(parent.clone());
+                    actions.push(RepairAction::CreatedLostAndFound {
+                        node: parent.clone(),
+                    });
+                }
+                edge_map.insert(txn, parent.to_string(), 0);
+                actions.push(RepairAction::ReattachedOrphan { node, parent });
+            }
+
+            let fi: String = container.get_as(txn, "fi").unwrap_or_default();
+            if FractionalIndex::from_string(&fi).is_err() {
+                reindex.insert(id.into());
+            }
+        }
+
+        // Every edge now points at a node that exists, so the only problem left for
+        // the structure to resolve is cycles
+        let mut structure = Self::new();
+        structure.insert_tiebreak = insert_tiebreak;
+        structure.init_from_yjs(map, txn)?;
+        for resolution in structure.resolutions.iter() {
+            if let ConflictResolution::CycleBroken {
+                reparented,
+                new_parent,
+                ..
+            } = resolution
+            {
+                actions.push(RepairAction::CycleBroken {
+                    node: reparented.clone(),
+                    new_parent: new_parent.clone(),
+                });
+            }
+        }
+        structure.apply_pending_edge_map_updates(map, txn)?;
+        structure.resolutions.clear();
+
+        for id in reindex {
+            let parent = structure.get_parent(&id).cloned().unwrap_or_default();
+            let last = structure
+                .get_children(&parent)
+                .unwrap_or_default()
+                .iter()
+                .filter(|child| **child != id)
+                .map(|child| &structure.nodes[child].fi)
+                .max();
+            let fi = last.map(FractionalIndex::new_after).unwrap_or_default();
+
+            let Some(Out::YMap(container)) = map.get(txn, id.as_ref()) else {
+                continue;
+            };
+            container.insert(txn, "fi", fi.to_string());
+            structure.nodes.get_mut(&id).unwrap().fi = fi;
+            if !actions.contains(&RepairAction::CreatedLostAndFound { node: id.clone() }) {
+                actions.push(RepairAction::Reindexed { node: id });
+            }
+        }
+        structure.init_from_yjs(map, txn)?;
+
+        Ok((structure, actions))
+    }
+
     pub(crate) fn has_pending_edge_map_updates(&self) -> bool {
         !self.pending_edge_map_updates.is_empty()
     }
@@ -498,7 +1617,7 @@ impl TreeStructure {
         txn: &mut yrs::TransactionMut,
     ) -> Result<()> {
         for (node_id, edge_id, edge_val) in self.pending_edge_map_updates.iter() {
-            let yrs::Out::YMap(container) = map.get(txn, &node_id.to_string()).unwrap() else {
+            let yrs::Out::YMap(container) = map.get(txn, node_id.as_ref()).unwrap() else {
                 return Err(
                     TreeError::TreePoisoned(Box::new(TreeError::BadYrsDoc(format!(
                         "Node is not a container: {}",
@@ -594,6 +1713,86 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn handles_cycles_without_outside_parent() -> Result<()> {
+        let doc = Doc::new();
+        let map = doc.get_or_insert_map("test");
+        let mut txn = doc.transact_mut();
+
+        let fi = FractionalIndex::default();
+
+        // "a" has only ever been under "b", which has to be reattached first
+        create_container(&map, &mut txn, "a", &fi, vec![("b".to_string(), 1)]);
+        create_container(
+            &map,
+            &mut txn,
+            "b",
+            &fi,
+            vec![("c".to_string(), 1), ("<ROOT>".to_string(), 0)],
+        );
+        create_container(&map, &mut txn, "c", &fi, vec![("a".to_string(), 1)]);
+        // "x" and "y" have only ever been under each other
+        create_container(&map, &mut txn, "x", &fi, vec![("y".to_string(), 0)]);
+        create_container(&map, &mut txn, "y", &fi, vec![("x".to_string(), 0)]);
+        drop(txn);
+
+        let txn = doc.transact_mut();
+        let mut tree = TreeStructure::new();
+        tree.init_from_yjs(&map, &txn)?;
+
+        assert_eq!(tree.get_parent(&"b".into()), Some(&NodeId::Root));
+        assert_eq!(tree.get_parent(&"a".into()), Some(&"b".into()));
+        assert_eq!(tree.get_parent(&"c".into()), Some(&"a".into()));
+        assert_eq!(tree.get_parent(&"x".into()), Some(&NodeId::Root));
+        assert_eq!(tree.get_parent(&"y".into()), Some(&"x".into()));
+        assert!(tree.check_integrity().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn checks_integrity() -> Result<()> {
+        let doc = Doc::new();
+        let map = doc.get_or_insert_map("test");
+        let mut txn = doc.transact_mut();
+
+        let fi1 = FractionalIndex::default();
+        let fi2 = FractionalIndex::new_after(&fi1);
+
+        create_container(&map, &mut txn, "1", &fi1, vec![("<ROOT>".to_string(), 0)]);
+        create_container(&map, &mut txn, "2", &fi2, vec![("<ROOT>".to_string(), 0)]);
+        create_container(&map, &mut txn, "3", &fi1, vec![("1".to_string(), 0)]);
+        drop(txn);
+
+        let txn = doc.transact_mut();
+        let mut tree = TreeStructure::new();
+        tree.init_from_yjs(&map, &txn)?;
+        assert!(tree.check_integrity().is_empty());
+
+        let root = tree.nodes.get_mut(&NodeId::Root).unwrap();
+        root.children.reverse();
+        root.children.push("4".into());
+        tree.nodes.get_mut(&"3".into()).unwrap().parent_id = Some("2".into());
+
+        assert_eq!(
+            tree.check_integrity(),
+            vec![
+                IntegrityError::MissingChild {
+                    parent: NodeId::Root,
+                    child: "4".into(),
+                },
+                IntegrityError::Misordered(NodeId::Root),
+                IntegrityError::ParentMismatch {
+                    node: "3".into(),
+                    parent: "2".into(),
+                    listed_under: vec!["1".into()],
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn handles_cycles() -> Result<()> {
         let doc = Doc::new();
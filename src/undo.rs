@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use crate::{Result, Tree};
+
+/// A handle to a tree's undo history, returned by [`Tree::undo_manager`], for code
+/// that should be able to undo and redo changes without otherwise changing the tree.
+///
+/// The history is kept by a Yrs [`UndoManager`](yrs::UndoManager) scoped to the
+/// tree's map in the Yrs document, which the tree creates when
+/// [`TreeOptions::undo`](crate::TreeOptions::undo) is enabled. Only changes made by
+/// this tree are recorded; changes received from other clients aren't undone. Each
+/// tree operation is a single step, including a cascading delete and everything in a
+/// [`Tree::transact`] call, and the tree updates its structure from the changes an
+/// undo or redo makes, as it does for any other change to the document.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use yrs_tree::{DeleteStrategy, NodeApi, Tree, TreeOptions};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let doc = Arc::new(yrs::Doc::new());
+/// let options = TreeOptions {
+///     undo: true,
+///     ..Default::default()
+/// };
+/// let tree = Tree::new_with_options(doc, "test", options)?;
+/// let undo = tree.undo_manager();
+///
+/// let folder = tree.create_child_with_id("folder")?;
+/// folder.create_child_with_id("file")?;
+/// undo.stop_capturing();
+///
+/// folder.delete(DeleteStrategy::Cascade)?;
+/// assert!(undo.undo()?);
+/// assert!(tree.has_node("file"));
+/// assert!(undo.can_redo());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TreeUndoManager {
+    tree: Arc<Tree>,
+}
+
+impl TreeUndoManager {
+    pub(crate) fn new(tree: Arc<Tree>) -> Self {
+        Self { tree }
+    }
+
+    /// Undoes the most recent change made to the tree by this client; see
+    /// [`Tree::undo`].
+    pub fn undo(&self) -> Result<bool> {
+        self.tree.undo()
+    }
+
+    /// Reapplies the most recent change reverted by [`TreeUndoManager::undo`]; see
+    /// [`Tree::redo`].
+    pub fn redo(&self) -> Result<bool> {
+        self.tree.redo()
+    }
+
+    /// Returns `true` if there is a change that [`TreeUndoManager::undo`] would revert.
+    pub fn can_undo(&self) -> bool {
+        self.tree.can_undo()
+    }
+
+    /// Returns `true` if there is a change that [`TreeUndoManager::redo`] would
+    /// reapply.
+    pub fn can_redo(&self) -> bool {
+        self.tree.can_redo()
+    }
+
+    /// Ends the current undo step; see [`Tree::stop_capturing`].
+    pub fn stop_capturing(&self) {
+        self.tree.stop_capturing()
+    }
+}
@@ -0,0 +1,295 @@
+//! JavaScript bindings for running a tree in a browser or web worker, built with
+//! `wasm-bindgen` when the `wasm` feature is enabled and the target is `wasm32`.
+//!
+//! A [`Tree`] isn't `Send`, and is shared with [`Arc`] only for its reference count,
+//! so it works as-is on WebAssembly, which has a single thread. Event callbacks must be
+//! `Send + Sync`, which JavaScript functions aren't; [`JsCallback`] asserts that they
+//! are, which holds as long as the module isn't built with the `atomics` target
+//! feature, so the bindings are disabled in that case.
+
+use std::sync::Arc;
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::{events::Subscription, DeleteStrategy, NodeApi, NodeEvent, NodeId, Tree, TreeOptions};
+
+/// A tree with its own Yrs document, for use from JavaScript. Node IDs are strings,
+/// with `"<ROOT>"` for the root node, and node data can be any value that
+/// `serde-wasm-bindgen` can convert to and from a Yrs value.
+///
+/// To sync the tree with other clients, such as a Yjs document on the main thread,
+/// exchange updates with `encodeState`, `encodeStateFrom`, `stateVector`, and
+/// `applyUpdate`.
+#[wasm_bindgen]
+pub struct JsTree {
+    tree: Arc<Tree>,
+}
+
+#[wasm_bindgen]
+impl JsTree {
+    /// Creates a tree with the given name in a new document. Yrs can't generate a
+    /// random client ID without access to the JavaScript environment, so unless one is
+    /// given, it's generated with `Math.random`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(name: &str, client_id: Option<u32>) -> Result<JsTree, JsError> {
+        let client_id =
+            client_id.unwrap_or_else(|| (js_sys::Math::random() * u32::MAX as f64) as u32);
+        let doc = Arc::new(yrs::Doc::with_client_id(client_id as u64));
+        let tree = Tree::new_with_options(doc, name, TreeOptions::default())?;
+        Ok(JsTree { tree })
+    }
+
+    /// Creates a child of `parent`, returning its ID. The ID is generated unless one
+    /// is given.
+    #[wasm_bindgen(js_name = createChild)]
+    pub fn create_child(&self, parent: &str, id: Option<String>) -> Result<String, JsError> {
+        let parent = self.node(parent)?;
+        let node = match id {
+            Some(id) => parent.create_child_with_id(id)?,
+            None => parent.create_child()?,
+        };
+        Ok(node.id().to_string())
+    }
+
+    /// Moves the node to the given parent, at the given index, or after the parent's
+    /// other children.
+    #[wasm_bindgen(js_name = moveTo)]
+    pub fn move_to(&self, id: &str, parent: &str, index: Option<usize>) -> Result<(), JsError> {
+        let parent = self.node(parent)?;
+        self.node(id)?.move_to(&parent, index)?;
+        Ok(())
+    }
+
+    /// Deletes the node, along with its descendants if `cascade` is true; otherwise,
+    /// its children are moved to its parent.
+    pub fn delete(&self, id: &str, cascade: bool) -> Result<(), JsError> {
+        let strategy = if cascade {
+            DeleteStrategy::Cascade
+        } else {
+            DeleteStrategy::Promote
+        };
+        self.node(id)?.delete(strategy)?;
+        Ok(())
+    }
+
+    /// Returns the value stored on the node at the given key, or `undefined`.
+    pub fn get(&self, id: &str, key: &str) -> Result<JsValue, JsError> {
+        let id = self.node(id)?.id().clone();
+        match self.tree.get_data_any(&id, key) {
+            Some(value) => Ok(serde_wasm_bindgen::to_value(&value)?),
+            None => Ok(JsValue::UNDEFINED),
+        }
+    }
+
+    /// Stores the value on the node at the given key.
+    pub fn set(&self, id: &str, key: &str, value: JsValue) -> Result<(), JsError> {
+        let value: yrs::Any = serde_wasm_bindgen::from_value(value)?;
+        self.node(id)?.set(key, value)?;
+        Ok(())
+    }
+
+    /// Returns the IDs of the node's children, in order.
+    pub fn children(&self, id: &str) -> Result<Vec<String>, JsError> {
+        Ok(self
+            .node(id)?
+            .children_ids()
+            .iter()
+            .map(|id| id.to_string())
+            .collect())
+    }
+
+    /// Returns the ID of the node's parent, or `undefined` for the root node.
+    pub fn parent(&self, id: &str) -> Result<Option<String>, JsError> {
+        Ok(self
+            .node(id)?
+            .parent()
+            .map(|parent| parent.id().to_string()))
+    }
+
+    /// Calls `callback` with an array of the node events caused by each change to the
+    /// tree, local or remote. Each event is an object with a `type` of `"created"`,
+    /// `"deleted"`, `"moved"`, or `"dataChanged"`, and the node's `id`; move events
+    /// also have `oldParent`, `oldIndex`, `newParent`, and `newIndex`. Call `free` on
+    /// the returned subscription to stop.
+    ///
+    /// The callback runs while the change is being committed, so it can read the
+    /// structure of the tree, but not change it or read node data.
+    #[wasm_bindgen(js_name = onChange)]
+    pub fn on_change(&self, callback: js_sys::Function) -> JsSubscription {
+        let callback = JsCallback(callback);
+        let subscription = self.tree.on_node_events(move |events| {
+            let events = events.iter().map(JsNodeEvent::from).collect::<Vec<_>>();
+            if let Ok(events) = serde_wasm_bindgen::to_value(&events) {
+                let _ = callback.0.call1(&JsValue::NULL, &events);
+            }
+        });
+        JsSubscription {
+            _subscription: subscription,
+        }
+    }
+
+    /// Returns the tree as a string, as drawn by [`Tree::pretty_print`].
+    #[wasm_bindgen(js_name = prettyPrint)]
+    pub fn pretty_print(&self) -> String {
+        self.tree.pretty_print()
+    }
+
+    /// Encodes the whole state of the document as a Yrs update.
+    #[wasm_bindgen(js_name = encodeState)]
+    pub fn encode_state(&self) -> Result<Vec<u8>, JsError> {
+        Ok(self.tree.encode_state()?)
+    }
+
+    /// Encodes the changes that a client with the given state vector hasn't seen.
+    #[wasm_bindgen(js_name = encodeStateFrom)]
+    pub fn encode_state_from(&self, state_vector: &[u8]) -> Result<Vec<u8>, JsError> {
+        Ok(self.tree.encode_state_from(state_vector)?)
+    }
+
+    /// Encodes the state vector of the document.
+    #[wasm_bindgen(js_name = stateVector)]
+    pub fn state_vector(&self) -> Result<Vec<u8>, JsError> {
+        Ok(self.tree.state_vector()?)
+    }
+
+    /// Applies an update from another client, such as one from `Y.encodeStateAsUpdate`.
+    #[wasm_bindgen(js_name = applyUpdate)]
+    pub fn apply_update(&self, update: &[u8]) -> Result<(), JsError> {
+        self.tree.apply_update(update)?;
+        Ok(())
+    }
+}
+
+impl JsTree {
+    fn node(&self, id: &str) -> Result<Arc<crate::Node>, JsError> {
+        self.tree
+            .get_node(id)
+            .ok_or_else(|| JsError::new(&format!("No node with ID {}", id)))
+    }
+}
+
+/// A subscription created with `JsTree.onChange`, which lasts until `free` is called.
+#[wasm_bindgen]
+pub struct JsSubscription {
+    _subscription: Subscription,
+}
+
+/// A JavaScript function used as an event callback.
+struct JsCallback(js_sys::Function);
+
+// SAFETY: without the `atomics` target feature, a WebAssembly module has a single
+// thread, so the function is never used from another thread.
+unsafe impl Send for JsCallback {}
+unsafe impl Sync for JsCallback {}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum JsNodeEvent {
+    Created {
+        id: String,
+    },
+    Deleted {
+        id: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Moved {
+        id: String,
+        old_parent: String,
+        old_index: usize,
+        new_parent: String,
+        new_index: usize,
+    },
+    DataChanged {
+        id: String,
+    },
+}
+
+impl From<&NodeEvent> for JsNodeEvent {
+    fn from(event: &NodeEvent) -> Self {
+        let id = |id: &NodeId| id.to_string();
+        match event {
+            NodeEvent::Created(node) => JsNodeEvent::Created { id: id(node) },
+            NodeEvent::Deleted(node) => JsNodeEvent::Deleted { id: id(node) },
+            NodeEvent::Moved {
+                id: node,
+                old_parent,
+                old_index,
+                new_parent,
+                new_index,
+            } => JsNodeEvent::Moved {
+                id: id(node),
+                old_parent: id(old_parent),
+                old_index: *old_index,
+                new_parent: id(new_parent),
+                new_index: *new_index,
+            },
+            NodeEvent::DataChanged(node) => JsNodeEvent::DataChanged { id: id(node) },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::JsTree;
+
+    #[wasm_bindgen_test]
+    fn test_js_tree() {
+        let tree = JsTree::new("test", Some(1)).unwrap();
+
+        let events = Rc::new(RefCell::new(vec![]));
+        let events_clone = events.clone();
+        let callback = Closure::<dyn Fn(JsValue)>::new(move |batch: JsValue| {
+            let batch: js_sys::Array = batch.unchecked_into();
+            for event in batch.iter() {
+                let kind = js_sys::Reflect::get(&event, &"type".into()).unwrap();
+                let id = js_sys::Reflect::get(&event, &"id".into()).unwrap();
+                events_clone.borrow_mut().push(format!(
+                    "{} {}",
+                    kind.as_string().unwrap(),
+                    id.as_string().unwrap()
+                ));
+            }
+        });
+        let subscription = tree.on_change(
+            callback
+                .as_ref()
+                .unchecked_ref::<js_sys::Function>()
+                .clone(),
+        );
+
+        let a = tree.create_child("<ROOT>", Some("A".to_string())).unwrap();
+        let b = tree.create_child("<ROOT>", None).unwrap();
+        tree.move_to(&b, &a, None).unwrap();
+        tree.set(&a, "name", "Folder".into()).unwrap();
+
+        assert_eq!(tree.children("<ROOT>").unwrap(), vec!["A"]);
+        assert_eq!(tree.parent(&b).unwrap(), Some("A".to_string()));
+        assert_eq!(tree.get(&a, "name").unwrap().as_string().unwrap(), "Folder");
+        assert!(tree.get(&a, "missing").unwrap().is_undefined());
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                "created A".to_string(),
+                format!("created {}", b),
+                format!("moved {}", b),
+                "dataChanged A".to_string(),
+            ]
+        );
+
+        // Updates from one tree can be applied to another
+        let other = JsTree::new("test", Some(2)).unwrap();
+        other.apply_update(&tree.encode_state().unwrap()).unwrap();
+        assert_eq!(other.pretty_print(), tree.pretty_print());
+
+        drop(subscription);
+        tree.delete(&a, true).unwrap();
+        assert_eq!(events.borrow().len(), 4);
+        assert!(tree.children("<ROOT>").unwrap().is_empty());
+    }
+}